@@ -1,7 +1,7 @@
 use wasm_bindgen::prelude::*;
 use std::rc::Rc;
 use crate::scope::{ComponentScope, NilRef};
-use crate::v_node::{h, ct, ComponentModel};
+use crate::v_node::{h, ct, strict, ComponentModel};
 use crate::dom::{VDomNode, ordered_children, hd, t, VDom, VDomElement};
 use im_rc::vector::Vector;
 use crate::components::root;
@@ -53,7 +53,7 @@ impl ComponentModel<VDom, ()> for Model {
             root::ViewType::Completed => "completed"
         }));
         let (style, style_handle) = scope.use_state(style_context::StyleType::Light);
-        ct(style,
+        strict(ct(&style_context::STYLE_CONTEXT, style,
             *ordered_children(vec![
                 hd(VDomElement {
                     tag_name: "div",
@@ -109,7 +109,7 @@ impl ComponentModel<VDom, ()> for Model {
                     })
                 }, None)
             ])
-        )
+        ))
     }
 }
 
@@ -53,7 +53,7 @@ impl ComponentModel<VDom, ()> for Props {
                         })),
                         ("keydown", scope.use_callback(enclose! { (new_task_name) move |scope, event: web_sys::Event| {
                             let key_code = event.dyn_into::<web_sys::KeyboardEvent>().unwrap().key_code();
-                            if key_code == 13 && new_task_name.len() > 0 {
+                            if key_code == 13 && !new_task_name.is_empty() {
                                 new_task_name_handle.update(scope, String::from(""));
                                 on_add_task.trigger(new_task_name.clone());
                             }
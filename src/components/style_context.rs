@@ -1,5 +1,9 @@
+use crate::scope::Context;
+
 #[derive(Clone, PartialEq, Copy)]
 pub enum StyleType {
     Dark,
     Light
 }
+
+pub static STYLE_CONTEXT: Context<StyleType> = Context::new(|| StyleType::Light);
@@ -28,7 +28,7 @@ impl ComponentModel<VDom, ()> for Model {
         let on_update_task = &self.on_update_task;
         let id = task.id;
         let completed = task.completed;
-        let style = scope.use_context::<style_context::StyleType>();
+        let style = scope.use_context(&style_context::STYLE_CONTEXT);
         hd(VDomElement {
             tag_name: "div",
             listeners: vec![
@@ -0,0 +1,72 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use crate::scope::RefObject;
+
+pub type DomListener = Closure<dyn Fn(web_sys::Event)>;
+
+// A change to the page recorded while reconciling. Nodes are created detached
+// during render; nothing a user can see changes until the list is committed.
+pub enum DomMutation {
+    Insert { parent: web_sys::HtmlElement, node: web_sys::Node, before: Option<web_sys::Node> },
+    Move { parent: web_sys::HtmlElement, node: web_sys::Node, before: Option<web_sys::Node> },
+    Remove { node: web_sys::Node },
+    SetAttribute { element: web_sys::HtmlElement, key: &'static str, value: String },
+    RemoveAttribute { element: web_sys::HtmlElement, key: &'static str },
+    SetValue { element: web_sys::HtmlElement, value: String },
+    SetStyle { element: web_sys::HtmlElement, key: &'static str, value: String },
+    RemoveStyle { element: web_sys::HtmlElement, key: &'static str },
+    SetText { node: web_sys::Text, text: String },
+    AddListener { element: web_sys::HtmlElement, event: &'static str, function: JsValue },
+    // Owns the old closure so the listener stays callable until it is detached.
+    RemoveListener { element: web_sys::HtmlElement, event: &'static str, listener: DomListener },
+    AttachRef { ref_object: RefObject<web_sys::HtmlElement>, element: web_sys::HtmlElement },
+    DetachRef { ref_object: RefObject<web_sys::HtmlElement> },
+}
+
+impl DomMutation {
+    pub fn is_ref(&self) -> bool {
+        matches!(self, DomMutation::AttachRef { .. } | DomMutation::DetachRef { .. })
+    }
+
+    pub fn apply(self) {
+        match self {
+            DomMutation::Insert { parent, node, before } | DomMutation::Move { parent, node, before } => {
+                parent.insert_before(&node, before.as_ref()).unwrap();
+            },
+            DomMutation::Remove { node } => {
+                // Nodes created and dropped within one update were never inserted.
+                if let Some(parent) = node.parent_node() {
+                    parent.remove_child(&node).unwrap();
+                }
+            },
+            DomMutation::SetAttribute { element, key, value } => element.set_attribute(key, &value).unwrap(),
+            DomMutation::RemoveAttribute { element, key } => element.remove_attribute(key).unwrap(),
+            DomMutation::SetValue { element, value } => element.dyn_ref::<web_sys::HtmlInputElement>().unwrap().set_value(&value),
+            DomMutation::SetStyle { element, key, value } => element.style().set_property(key, &value).unwrap(),
+            DomMutation::RemoveStyle { element, key } => {
+                element.style().remove_property(key).unwrap();
+            },
+            DomMutation::SetText { node, text } => node.set_text_content(Some(&text)),
+            DomMutation::AddListener { element, event, function } => {
+                element.add_event_listener_with_callback(event, function.unchecked_ref()).unwrap();
+            },
+            DomMutation::RemoveListener { element, event, listener } => {
+                element.remove_event_listener_with_callback(event, listener.as_ref().unchecked_ref()).unwrap();
+            },
+            DomMutation::AttachRef { ref_object, element } => ref_object.replace(Some(element)),
+            DomMutation::DetachRef { ref_object } => ref_object.replace(None),
+        }
+    }
+}
+
+// Applies the page changes in the order they were recorded, then points refs
+// at their elements so that effects running after the commit see both.
+pub fn commit_mutations(mutations: Vec<DomMutation>) {
+    let (refs, changes): (Vec<DomMutation>, Vec<DomMutation>) = mutations.into_iter().partition(|m| m.is_ref());
+    for mutation in changes.into_iter() {
+        mutation.apply();
+    }
+    for mutation in refs.into_iter() {
+        mutation.apply();
+    }
+}
@@ -1,9 +1,10 @@
 use wasm_bindgen::prelude::*;
 use std::collections::HashMap;
 use crate::dom::v_dom_node::{VDomNode, VDomElement, VDom};
+use crate::dom::dom_mutation::{DomMutation, DomListener, commit_mutations};
 use crate::v_node::VComponentElementT;
-use crate::scope::{RefObject, ContextLink, clone_context_link, Updater};
-use crate::renderer::{NativeMount, ComponentMount, NativeMountFactory, Mount};
+use crate::scope::{RefObject, ContextLink, clone_context_link, Updater, flush, batch, DeferredTask, Scheduler, Deadline, NoDeadline, SliceTask, Executor, LocalFuture, Clock, TimerId};
+use crate::renderer::{NativeMount, ComponentMount, NativeMountFactory, Mount, child_tree_path};
 use wasm_bindgen::JsCast;
 use std::rc::{Rc, Weak};
 use std::cell::{RefCell, Ref, RefMut};
@@ -11,30 +12,34 @@ use std::cell::{RefCell, Ref, RefMut};
 pub struct DomElementMount {
     root_dom_node: web_sys::HtmlElement,
     updater: Rc<RefCell<Updater>>,
-    listeners: Vec<(&'static str, wasm_bindgen::closure::Closure<dyn std::ops::Fn(web_sys::Event)>)>,
+    listeners: Vec<(&'static str, DomListener)>,
     style: HashMap<&'static str, String>,
     attributes: HashMap<&'static str, String>,
     children_mount: Option<Mount<VDom>>,
     dom_factory: Rc<DomMountFactory>,
     parent_dom_factory: Rc<DomMountFactory>,
     context_link: ContextLink,
+    tree_path: String,
     ref_object: Option<RefObject<web_sys::HtmlElement>>,
 }
 
 impl DomElementMount {
-    pub fn new(v_element: VDomElement, context_link: ContextLink, dom_factory: Rc<DomMountFactory>, updater: Rc<RefCell<Updater>>) -> DomElementMount {
+    pub fn new(v_element: VDomElement, context_link: ContextLink, tree_path: String, dom_factory: Rc<DomMountFactory>, updater: Rc<RefCell<Updater>>) -> DomElementMount {
         let window = web_sys::window().expect("no global `window` exists");
         let document = window.document().expect("should have a document on window");
-        let dom_element = document.create_element(&v_element.tag_name).unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+        let dom_element = document.create_element(v_element.tag_name).unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
         let listeners = v_element.listeners.into_iter().map(|(event, handle)| {
-            let listener: Box<dyn Fn(web_sys::Event) -> ()> = Box::new(move |event| {
-                handle.trigger(event);
+            let listener: Box<dyn Fn(web_sys::Event)> = Box::new(move |event| {
+                batch(|| handle.trigger(event));
             });
             (event, Closure::wrap(listener))
         }).collect();
-        v_element.ref_object.as_ref().map(|inner| {
-            inner.replace(Some(dom_element.clone()))
-        });
+        if let Some(ref_object) = v_element.ref_object.as_ref() {
+            dom_factory.record(DomMutation::AttachRef {
+                ref_object: ref_object.clone(),
+                element: dom_element.clone()
+            });
+        }
         let mut r = DomElementMount {
             updater,
             root_dom_node: dom_element.clone(),
@@ -42,9 +47,10 @@ impl DomElementMount {
             listeners,
             children_mount: None,
             attributes: v_element.attributes,
-            dom_factory: Rc::new(DomMountFactory::new(dom_element)),
+            dom_factory: Rc::new(DomMountFactory::new(dom_element, dom_factory.mutations.clone())),
             parent_dom_factory: dom_factory,
             context_link,
+            tree_path,
             ref_object: v_element.ref_object,
         };
         r.rerender(*v_element.children);
@@ -55,54 +61,91 @@ impl DomElementMount {
         self.children_mount = Some(if let Some(children_mount) = self.children_mount.take() {
             children_mount.update(children, self.dom_factory.clone(), self.updater.clone())
         } else {
-            Mount::new(children, clone_context_link(&self.context_link), self.dom_factory.clone(), self.updater.clone())
+            Mount::new(children, clone_context_link(&self.context_link), child_tree_path(&self.tree_path, "0"), self.dom_factory.clone(), self.updater.clone())
         });
         for (event, closure) in self.listeners.iter() {
-            let function = closure.as_ref().unchecked_ref();
-            self.root_dom_node.add_event_listener_with_callback(&event, function).unwrap();
+            self.dom_factory.record(DomMutation::AddListener {
+                element: self.root_dom_node.clone(),
+                event,
+                function: closure.as_ref().clone()
+            });
         }
         for (key, value) in self.style.iter() {
-            self.root_dom_node.style().set_property(key, value).unwrap();
+            self.dom_factory.record(DomMutation::SetStyle {
+                element: self.root_dom_node.clone(),
+                key,
+                value: value.clone()
+            });
         }
         for (key, value) in self.attributes.iter() {
-            match key {
-                &"value" => self.root_dom_node.dyn_ref::<web_sys::HtmlInputElement>().unwrap().set_value(value),
-                _ => self.root_dom_node.set_attribute(key, value).unwrap()
-            }
+            self.dom_factory.record(match key {
+                &"value" => DomMutation::SetValue {
+                    element: self.root_dom_node.clone(),
+                    value: value.clone()
+                },
+                _ => DomMutation::SetAttribute {
+                    element: self.root_dom_node.clone(),
+                    key,
+                    value: value.clone()
+                }
+            });
         }
     }
 
-    fn update(&mut self, new_node: VDomElement) {
-        for (event, closure) in self.listeners.iter() {
-            let function = closure.as_ref().unchecked_ref();
-            self.root_dom_node.remove_event_listener_with_callback(&event, function).unwrap();
+    fn remove_listeners(&mut self) {
+        for (event, listener) in std::mem::take(&mut self.listeners).into_iter() {
+            self.dom_factory.record(DomMutation::RemoveListener {
+                element: self.root_dom_node.clone(),
+                event,
+                listener
+            });
         }
+    }
+
+    fn update(&mut self, new_node: VDomElement) {
+        self.remove_listeners();
         for (key, _) in self.attributes.iter() {
-            self.root_dom_node.remove_attribute(key).unwrap();
+            self.dom_factory.record(DomMutation::RemoveAttribute {
+                element: self.root_dom_node.clone(),
+                key
+            });
         }
         for (key, _) in self.style.iter() {
-            self.root_dom_node.style().remove_property(key).unwrap();
+            self.dom_factory.record(DomMutation::RemoveStyle {
+                element: self.root_dom_node.clone(),
+                key
+            });
         }
         self.attributes = new_node.attributes;
         self.style = new_node.style;
         self.listeners = new_node.listeners.into_iter().map(|(event, handle)| {
-            let listener: Box<dyn Fn(web_sys::Event) -> ()> = Box::new(move |event| {
-                handle.trigger(event);
+            let listener: Box<dyn Fn(web_sys::Event)> = Box::new(move |event| {
+                batch(|| handle.trigger(event));
             });
             (event, Closure::wrap(listener))
         }).collect();
         if new_node.ref_object.is_some() {
-            self.ref_object.as_ref().map(|inner| { inner.replace(Some(self.root_dom_node.clone().dyn_into::<web_sys::HtmlElement>().unwrap())) });
+            if let Some(ref_object) = self.ref_object.as_ref() {
+                self.dom_factory.record(DomMutation::AttachRef {
+                    ref_object: ref_object.clone(),
+                    element: self.root_dom_node.clone()
+                });
+            }
         }
         self.dom_factory.reset_scanner();
         self.rerender(*new_node.children);
     }
 
     fn unmount(&mut self) {
-        self.root_dom_node.parent_element().unwrap().remove_child(&self.root_dom_node).unwrap();
-        self.ref_object.as_ref().map(|inner| { inner.replace(None) });
-        self.ref_object = None;
-        self.listeners = vec![];
+        self.parent_dom_factory.record(DomMutation::Remove {
+            node: self.root_dom_node.clone().dyn_into::<web_sys::Node>().ok().unwrap()
+        });
+        if let Some(ref_object) = self.ref_object.take() {
+            self.parent_dom_factory.record(DomMutation::DetachRef {
+                ref_object
+            });
+        }
+        self.remove_listeners();
         self.parent_dom_factory.remove_dom_child(self.root_dom_node.clone().dyn_into::<web_sys::Node>().ok().unwrap());
         if let Some(mut child) = self.children_mount.take() {
             child.unmount();
@@ -113,12 +156,13 @@ impl DomElementMount {
 pub struct DomTextMount {
     root_dom_node: web_sys::Text,
     context_link: ContextLink,
+    tree_path: String,
     parent_dom_factory: Rc<DomMountFactory>,
     text: String
 }
 
 impl DomTextMount {
-    pub fn new(text: String, context_link: ContextLink, parent_dom_factory: Rc<DomMountFactory>) -> DomTextMount {
+    pub fn new(text: String, context_link: ContextLink, tree_path: String, parent_dom_factory: Rc<DomMountFactory>) -> DomTextMount {
         let window = web_sys::window().expect("no global `window` exists");
         let document = window.document().expect("should have a document on window");
         
@@ -127,12 +171,16 @@ impl DomTextMount {
             root_dom_node: text_node,
             text,
             context_link,
+            tree_path,
             parent_dom_factory
         }
     }
 
     fn rerender(&self) {
-        self.root_dom_node.set_text_content(Some(&self.text));
+        self.parent_dom_factory.record(DomMutation::SetText {
+            node: self.root_dom_node.clone(),
+            text: self.text.clone()
+        });
     }
     fn update(&mut self, new_text: String) {
         self.text = new_text;
@@ -140,22 +188,24 @@ impl DomTextMount {
     }
 
     fn unmount(&mut self) {
-        self.root_dom_node.parent_element().unwrap().remove_child(&self.root_dom_node).unwrap();
+        self.parent_dom_factory.record(DomMutation::Remove {
+            node: self.root_dom_node.clone().dyn_into::<web_sys::Node>().ok().unwrap()
+        });
         self.parent_dom_factory.remove_dom_child(self.root_dom_node.clone().dyn_into::<web_sys::Node>().ok().unwrap());
     }
 }
 
 enum DomMount {
-    Element(DomElementMount),
+    Element(Box<DomElementMount>),
     Text(DomTextMount),
     None
 }
 
 impl DomMount{
-    fn new(vnode: VDom, context_link: ContextLink, dom_mount_factory: Rc<DomMountFactory>, updater: Rc<RefCell<Updater>>) -> DomMount {
+    fn new(vnode: VDom, context_link: ContextLink, tree_path: String, dom_mount_factory: Rc<DomMountFactory>, updater: Rc<RefCell<Updater>>) -> DomMount {
         match vnode {
-            VDom::Element(v_element) => DomMount::Element(DomElementMount::new(v_element, context_link, dom_mount_factory, updater)),
-            VDom::Text(v_text) => DomMount::Text(DomTextMount::new(v_text, context_link, dom_mount_factory))
+            VDom::Element(v_element) => DomMount::Element(Box::new(DomElementMount::new(v_element, context_link, tree_path, dom_mount_factory, updater))),
+            VDom::Text(v_text) => DomMount::Text(DomTextMount::new(v_text, context_link, tree_path, dom_mount_factory))
         }
     }
 
@@ -181,8 +231,9 @@ impl NativeMount<VDom> for DomMount {
                 DomMount::Text(text)
             }
             (mut m, vnode) => {
+                let tree_path = m.get_tree_path().to_string();
                 m.unmount();
-                DomMount::new(vnode, clone_context_link(self.get_context_link()), dom_mount_factory.downcast_rc::<DomMountFactory>().ok().unwrap(), updater)
+                DomMount::new(vnode, clone_context_link(self.get_context_link()), tree_path, dom_mount_factory.downcast_rc::<DomMountFactory>().ok().unwrap(), updater)
             }
         }
     }
@@ -201,6 +252,14 @@ impl NativeMount<VDom> for DomMount {
             DomMount::None => &None
         }
     }
+
+    fn get_tree_path(&self) -> &str {
+        match self {
+            DomMount::Element(element) => &element.tree_path,
+            DomMount::Text(text) => &text.tree_path,
+            DomMount::None => ""
+        }
+    }
 }
 
 enum DomChildren {
@@ -212,19 +271,26 @@ pub struct DomMountFactory {
     parent_dom_node: web_sys::HtmlElement,
     dom_children: RefCell<Vec<DomChildren>>,
     current_index: RefCell<usize>,
-    parent: Weak<DomMountFactory>
+    parent: Weak<DomMountFactory>,
+    // Shared by every factory of a root and applied as a whole on commit.
+    mutations: Rc<RefCell<Vec<DomMutation>>>
 }
 
 impl DomMountFactory {
-    fn new(parent_dom_node: web_sys::HtmlElement) -> DomMountFactory {
+    fn new(parent_dom_node: web_sys::HtmlElement, mutations: Rc<RefCell<Vec<DomMutation>>>) -> DomMountFactory {
         DomMountFactory {
             parent_dom_node,
             dom_children: RefCell::new(vec![]),
             current_index: RefCell::new(0),
-            parent: Weak::default()
+            parent: Weak::default(),
+            mutations
         }
     }
 
+    fn record(&self, mutation: DomMutation) {
+        self.mutations.try_borrow_mut().unwrap().push(mutation);
+    }
+
     fn remove_dom_child(&self, dom_node: web_sys::Node) {
         let mut dom_children = self.dom_children.try_borrow_mut().unwrap();
         let current_index = *{self.current_index.try_borrow().unwrap()};
@@ -324,6 +390,7 @@ impl DomMountFactory {
             }
             dom_children.remove(original_index);
         }
+        let is_move = original.is_some();
         let dom_child = dom_children.get(index);
         let ref_dom = if let Some(child) = dom_child {
             match child {
@@ -341,7 +408,12 @@ impl DomMountFactory {
             })
         });
         web_sys::console::log_4(&dom_node, &JsValue::from(ref_dom.as_ref()), &JsValue::from(index.to_string()), &JsValue::from(dom_children.len().to_string()));
-        self.parent_dom_node.insert_before(&dom_node, ref_dom.as_ref()).unwrap();
+        let parent = self.parent_dom_node.clone();
+        self.record(if is_move {
+            DomMutation::Move { parent, node: dom_node.clone(), before: ref_dom }
+        } else {
+            DomMutation::Insert { parent, node: dom_node.clone(), before: ref_dom }
+        });
         dom_children.insert(index, DomChildren::Dom(dom_node.clone()));
     }
 
@@ -370,12 +442,17 @@ impl NativeMountFactory<VDom> for DomMountFactory {
         }
     }
 
+    fn commit(&self) {
+        let mutations = std::mem::take(&mut *self.mutations.try_borrow_mut().unwrap());
+        commit_mutations(mutations);
+    }
+
     fn reset_scanner(&self) {
         *self.current_index.try_borrow_mut().unwrap() = 0;
     }
 
-    fn make_native_mount(self: Rc<Self>, vdom: VDom, context_link: ContextLink, updater: Rc<RefCell<Updater>>)-> Rc<RefCell<dyn NativeMount<VDom>>> {
-        let mount = DomMount::new(vdom, context_link, self.clone(), updater);
+    fn make_native_mount(self: Rc<Self>, vdom: VDom, context_link: ContextLink, tree_path: String, updater: Rc<RefCell<Updater>>)-> Rc<RefCell<dyn NativeMount<VDom>>> {
+        let mount = DomMount::new(vdom, context_link, tree_path, self.clone(), updater);
         let index = *{
             self.current_index.try_borrow().unwrap()
         };
@@ -437,7 +514,8 @@ impl NativeMountFactory<VDom> for DomMountFactory {
             parent_dom_node: self.parent_dom_node.clone(),
             dom_children: RefCell::new(vec![]),
             current_index: RefCell::new(0),
-            parent: Rc::downgrade(&self)
+            parent: Rc::downgrade(&self),
+            mutations: self.mutations.clone()
         });
 
         let index = {
@@ -451,7 +529,132 @@ impl NativeMountFactory<VDom> for DomMountFactory {
     }
 }
 
-pub fn mount_dom_component(element: Box<dyn VComponentElementT<VDom>>, root_dom_node: web_sys::HtmlElement, updater: Rc<RefCell<Updater>>) {
-    let factory = DomMountFactory::new(root_dom_node);
-    ComponentMount::new(element, None, Rc::new(factory), updater);
+pub fn mount_dom_component(element: Box<dyn VComponentElementT<VDom>>, root_dom_node: web_sys::HtmlElement, updater: Rc<RefCell<Updater>>) -> Rc<RefCell<ComponentMount<VDom>>> {
+    let factory = Rc::new(DomMountFactory::new(root_dom_node, Rc::new(RefCell::new(vec![]))));
+    let committer = factory.clone();
+    updater.try_borrow_mut().unwrap().set_committer(move || committer.commit());
+    let root = ComponentMount::new(element, None, String::from("r"), factory, updater.clone());
+    flush(&updater);
+    root
+}
+
+pub fn defer_to_next_tick(task: DeferredTask) {
+    let window = web_sys::window().expect("no global `window` exists");
+    let callback = Closure::once_into_js(move || {
+        task();
+    });
+    window.set_timeout_with_callback_and_timeout_and_arguments_0(callback.unchecked_ref(), 0).unwrap();
+}
+
+pub struct MicrotaskScheduler;
+
+impl Scheduler for MicrotaskScheduler {
+    fn schedule_flush(&self, flush: DeferredTask) {
+        wasm_bindgen_futures::spawn_local(async move {
+            flush();
+        });
+    }
+}
+
+// Not used by this app's root, which renders in idle time, but available to
+// roots that want DOM writes aligned with animation frames.
+#[allow(dead_code)]
+pub struct AnimationFrameScheduler;
+
+impl Scheduler for AnimationFrameScheduler {
+    fn schedule_flush(&self, flush: DeferredTask) {
+        let window = web_sys::window().expect("no global `window` exists");
+        let callback = Closure::once_into_js(move || {
+            flush();
+        });
+        window.request_animation_frame(callback.unchecked_ref()).unwrap();
+    }
+}
+
+struct IdleCallbackDeadline {
+    deadline: web_sys::IdleDeadline
+}
+
+impl Deadline for IdleCallbackDeadline {
+    fn should_yield(&self) -> bool {
+        self.deadline.time_remaining() <= 0.0
+    }
+}
+
+// Flushes in a microtask like `MicrotaskScheduler`, but runs time sliced
+// work in idle periods and yields when the browser's idle deadline is up.
+pub struct IdleScheduler;
+
+impl Scheduler for IdleScheduler {
+    fn schedule_flush(&self, flush: DeferredTask) {
+        MicrotaskScheduler.schedule_flush(flush);
+    }
+
+    fn schedule_slice(&self, slice: SliceTask) {
+        let window = web_sys::window().expect("no global `window` exists");
+        let slice = Rc::new(RefCell::new(Some(slice)));
+        let idle_slice = slice.clone();
+        let callback = Closure::once_into_js(move |deadline: web_sys::IdleDeadline| {
+            if let Some(slice) = idle_slice.try_borrow_mut().unwrap().take() {
+                slice(&IdleCallbackDeadline {
+                    deadline
+                });
+            }
+        });
+        // Browsers without idle callbacks run the slice on the next tick.
+        if window.request_idle_callback(callback.unchecked_ref()).is_err() {
+            defer_to_next_tick(Box::new(move || {
+                if let Some(slice) = slice.try_borrow_mut().unwrap().take() {
+                    slice(&NoDeadline);
+                }
+            }));
+        }
+    }
+}
+
+pub struct WasmExecutor;
+
+impl Executor for WasmExecutor {
+    fn spawn(&self, future: LocalFuture) {
+        wasm_bindgen_futures::spawn_local(future);
+    }
+}
+
+pub struct WindowClock {
+    intervals: RefCell<HashMap<TimerId, Closure<dyn Fn()>>>
+}
+
+impl WindowClock {
+    pub fn new() -> WindowClock {
+        WindowClock {
+            intervals: RefCell::new(HashMap::new())
+        }
+    }
+}
+
+impl Clock for WindowClock {
+    fn set_timeout(&self, delay_ms: u32, callback: Box<dyn FnOnce()>) -> TimerId {
+        let window = web_sys::window().expect("no global `window` exists");
+        let callback = Closure::once_into_js(move || {
+            callback();
+        });
+        window.set_timeout_with_callback_and_timeout_and_arguments_0(callback.unchecked_ref(), delay_ms as i32).unwrap() as TimerId
+    }
+
+    fn set_interval(&self, period_ms: u32, callback: Box<dyn Fn()>) -> TimerId {
+        let window = web_sys::window().expect("no global `window` exists");
+        let closure = Closure::wrap(callback);
+        let id = window.set_interval_with_callback_and_timeout_and_arguments_0(closure.as_ref().unchecked_ref(), period_ms as i32).unwrap() as TimerId;
+        self.intervals.try_borrow_mut().unwrap().insert(id, closure);
+        id
+    }
+
+    fn clear(&self, id: TimerId) {
+        let window = web_sys::window().expect("no global `window` exists");
+        if self.intervals.try_borrow_mut().unwrap().remove(&id).is_some() {
+            window.clear_interval_with_handle(id as i32);
+        } else {
+            window.clear_timeout_with_handle(id as i32);
+        }
+    }
 }
\ No newline at end of file
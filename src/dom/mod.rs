@@ -1,6 +1,9 @@
 #[macro_use]
 mod v_dom_node;
 mod dom_renderer;
+mod dom_mutation;
 
-pub use crate::dom::dom_renderer::mount_dom_component;
+// Every scheduler is exported so a root can pick one; `start` only uses one.
+#[allow(unused_imports)]
+pub use crate::dom::dom_renderer::{mount_dom_component, defer_to_next_tick, MicrotaskScheduler, AnimationFrameScheduler, IdleScheduler, WasmExecutor, WindowClock};
 pub use crate::dom::v_dom_node::{VDomNode, ordered_children, hd, t, VDom, VDomElement};
\ No newline at end of file
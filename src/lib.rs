@@ -7,25 +7,40 @@ use components::{app};
 use std::rc::Rc;
 use crate::v_node::VComponentElement;
 use crate::scope::{Updater};
+use crate::renderer::ComponentMount;
+use crate::dom::VDom;
 
 mod v_node;
 #[macro_use]
 mod dom;
 mod renderer;
 mod components;
-mod scope;
+pub mod scope;
 
+thread_local! {
+    // Components only hold weak references to their own mount, so the root
+    // has to be owned somewhere for the app to stay alive.
+    static ROOT: RefCell<Option<Rc<RefCell<ComponentMount<VDom>>>>> = const { RefCell::new(None) };
+}
 
 #[wasm_bindgen(start)]
-pub fn start() -> () {
+pub fn start() {
     console_error_panic_hook::set_once();
     let window = web_sys::window().expect("no global `window` exists");
     let document = window.document().expect("should have a document on window");
-    crate::dom::mount_dom_component(
+    let mut updater = Updater::new();
+    updater.set_scheduler(Rc::new(crate::dom::IdleScheduler));
+    updater.set_passive_effect_scheduler(crate::dom::defer_to_next_tick);
+    updater.set_executor(Rc::new(crate::dom::WasmExecutor));
+    updater.set_clock(Rc::new(crate::dom::WindowClock::new()));
+    let root = crate::dom::mount_dom_component(
         Box::new(VComponentElement::new(
             app::Model {
             },
            None 
         )),
-        document.body().unwrap().query_selector("#mount").unwrap().unwrap().dyn_into::<web_sys::HtmlElement>().unwrap(), Rc::new(RefCell::new(Updater::new())));
+        document.body().unwrap().query_selector("#mount").unwrap().unwrap().dyn_into::<web_sys::HtmlElement>().unwrap(), Rc::new(RefCell::new(updater)));
+    ROOT.with(|r| {
+        *r.borrow_mut() = Some(root);
+    });
 }
\ No newline at end of file
@@ -1,37 +1,58 @@
 use crate::v_node::{VComponentElementT, NodeComparisonResult};
-use crate::scope::{Updater, Scope, ContextLink, Renderer};
+use crate::scope::{Updater, Scope, enter_render, leave_render, current_lane, ContextLink, Renderer, is_strict, start_render, end_render, warn, STRICT_RENDER_LIMIT};
+#[cfg(debug_assertions)]
+use crate::scope::track_component_mount;
 use std::rc::Rc;
 use std::cell::{RefCell};
 use crate::renderer::native::NativeMountFactory;
-use crate::renderer::mount::Mount;
+use crate::renderer::mount::{Mount, child_tree_path};
 
 pub struct ComponentMount<VNativeNode: 'static> {
     updater: Rc<RefCell<Updater>>,
     pub scope: Option<Scope>,
     element: Box<dyn VComponentElementT<VNativeNode>>,
     content: Option<Mount<VNativeNode>>,
+    pub tree_path: String,
     pub native_mount_factory: Rc<dyn NativeMountFactory<VNativeNode>>,
+    strict: bool,
+    render_count: (usize, usize),
+    depth: usize,
 }
 
 impl<VNativeNode: 'static> ComponentMount<VNativeNode> {
-    pub fn new(element: Box<dyn VComponentElementT<VNativeNode>>, context_link: ContextLink, native_mount_factory: Rc<dyn NativeMountFactory<VNativeNode>>, updater: Rc<RefCell<Updater>>) -> Rc<RefCell<ComponentMount<VNativeNode>>> {
+    pub fn new(element: Box<dyn VComponentElementT<VNativeNode>>, context_link: ContextLink, tree_path: String, native_mount_factory: Rc<dyn NativeMountFactory<VNativeNode>>, updater: Rc<RefCell<Updater>>) -> Rc<RefCell<ComponentMount<VNativeNode>>> {
+        #[cfg(debug_assertions)]
+        track_component_mount(1);
+        let strict = is_strict(&context_link);
+        let depth = updater.try_borrow().unwrap().render_depth();
         let renderer = Rc::new(RefCell::new(ComponentMount {
             updater,
             scope: None,
             element,
             content: None,
+            tree_path: tree_path.clone(),
             native_mount_factory: native_mount_factory.component_native_mount_factory(),
+            strict,
+            render_count: (0, 0),
+            depth,
         }));
 
         let r: Rc<RefCell<dyn Renderer>> = renderer.clone();
 
-        let scope = Scope::new(r, context_link);
+        let mut scope = Scope::new(r, context_link, tree_path);
+        if strict {
+            scope.mark_strict_remount();
+        }
 
         let r = renderer.clone();
         let mut renderer_mut = r.try_borrow_mut().unwrap();
 
+        // The scope stays unrendered, with no content, until its turn comes.
+        let deferred = renderer_mut.defer_render(&mut scope);
         renderer_mut.scope = Some(scope);
-        renderer_mut.rerender();
+        if !deferred {
+            renderer_mut.rerender();
+        }
 
         renderer
     }
@@ -44,57 +65,110 @@ impl<VNativeNode: 'static> ComponentMount<VNativeNode> {
                 self.scope = Some(scope);
             },
             NodeComparisonResult::SameType => {
-                self.scope = Some(scope);
                 self.element = element;
-                self.native_mount_factory.reset_scanner();
-                self.rerender();
+                let deferred = self.defer_render(&mut scope);
+                self.scope = Some(scope);
+                if !deferred {
+                    self.native_mount_factory.reset_scanner();
+                    self.rerender();
+                }
             },
             NodeComparisonResult::DifferentType => {
-                self.unmount();
-                scope.cleanup();
+                if let Some(mut content) = self.content.take() {
+                    content.unmount();
+                }
+                let effects = scope.cleanup();
+                self.updater.try_borrow_mut().unwrap().enqueue_unmount_cleanups(effects);
                 scope.reset();
+                if self.strict {
+                    scope.mark_strict_remount();
+                }
                 self.element = element;
+                let deferred = self.defer_render(&mut scope);
                 self.scope = Some(scope);
-                self.rerender();
+                if !deferred {
+                    self.rerender();
+                }
             }
         }
     }
 
-    fn rerender(&mut self) -> () {
+    // Time sliced roots render each component in its own unit of work, after
+    // the parent that created or updated it.
+    fn defer_render(&self, scope: &mut Scope) -> bool {
+        let mut updater = self.updater.try_borrow_mut().unwrap();
+        if !updater.is_time_sliced() {
+            return false;
+        }
+        scope.mark_update();
+        if let Some(renderer) = scope.renderer().upgrade() {
+            updater.mark_update(&renderer, self.depth, current_lane());
+        }
+        true
+    }
+
+    fn rerender(&mut self) {
+        enter_render(&self.updater);
         self.scope.as_mut().unwrap().clear_update();
-        let render_result = self.element.render(&mut self.scope.as_mut().unwrap());
+        start_render(self.element.name(), self.strict);
+        if self.strict {
+            self.check_render_count();
+            // Render an extra time and throw the result away so renders that
+            // depend on side effects produce visibly inconsistent output.
+            let _ = self.element.render(self.scope.as_mut().unwrap());
+        }
+        let render_result = self.element.render(self.scope.as_mut().unwrap());
+        end_render();
+        let parent_depth = self.updater.try_borrow_mut().unwrap().set_render_depth(self.depth + 1);
         if let Some(current_mount) = self.content.take() {
             self.content = Some(current_mount.update(render_result, self.native_mount_factory.clone(), self.updater.clone()))
         } else {
-            self.content = Some(Mount::new(render_result, self.scope.as_ref().unwrap().clone_context_link(), self.native_mount_factory.clone(), self.updater.clone()));
+            self.content = Some(Mount::new(render_result, self.scope.as_ref().unwrap().clone_context_link(), child_tree_path(&self.tree_path, "0"), self.native_mount_factory.clone(), self.updater.clone()));
         }
+        let mut updater = self.updater.try_borrow_mut().unwrap();
+        updater.set_render_depth(parent_depth);
+        updater.enqueue_effects(self.scope.as_mut().unwrap(), self.depth);
+        leave_render();
     }
 
-    pub fn unmount(&mut self) -> () {
+    fn check_render_count(&mut self) {
+        let flush_count = self.updater.try_borrow().unwrap().flush_count();
+        let (last_flush, count) = self.render_count;
+        let count = if last_flush == flush_count { count + 1 } else { 1 };
+        self.render_count = (flush_count, count);
+        if count == STRICT_RENDER_LIMIT {
+            warn(&format!("component `{}` rendered {} times in a single update; look for callbacks or effects that set state unconditionally", self.element.name(), count));
+        }
+    }
+
+    pub fn unmount(&mut self) {
         if let Some(mut content) = self.content.take() {
             content.unmount();
         }
         self.native_mount_factory.clone().on_unmount();
-        self.scope.as_mut().unwrap().cleanup();
+        let effects = self.scope.as_mut().unwrap().cleanup();
+        self.updater.try_borrow_mut().unwrap().enqueue_unmount_cleanups(effects);
         self.scope = None;
         self.content = None;
     }
 
     pub fn consume_update(&mut self) {
-        match self.scope.as_ref() {
-            Some(scope) => {
-                if scope.has_update() {
-                    self.native_mount_factory.reset_scanner();
-                    self.rerender();
-                }
-            }
-            None => {
-
+        if let Some(scope) = self.scope.as_ref() {
+            if scope.has_update() {
+                self.native_mount_factory.reset_scanner();
+                self.rerender();
             }
         }
     }
 }
 
+#[cfg(debug_assertions)]
+impl<VNativeNode: 'static> Drop for ComponentMount<VNativeNode> {
+    fn drop(&mut self) {
+        track_component_mount(-1);
+    }
+}
+
 impl<VNativeNode: 'static> Renderer for ComponentMount<VNativeNode> {
     fn maybe_update(&mut self) {
         self.consume_update();
@@ -107,4 +181,12 @@ impl<VNativeNode: 'static> Renderer for ComponentMount<VNativeNode> {
     fn updater(&self) -> Rc<RefCell<Updater>> {
         self.updater.clone()
     }
+
+    fn is_mounted(&self) -> bool {
+        self.scope.is_some()
+    }
+
+    fn depth(&self) -> usize {
+        self.depth
+    }
 }
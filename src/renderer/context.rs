@@ -3,22 +3,24 @@ use crate::scope::{Updater, ContextLink, ContextNodeT};
 use std::rc::Rc;
 use std::cell::{RefCell};
 use crate::renderer::native::NativeMountFactory;
-use crate::renderer::mount::Mount;
+use crate::renderer::mount::{Mount, child_tree_path};
 
 pub struct ContextMount<VNativeNode: 'static> {
     updater: Rc<RefCell<Updater>>,
     pub context_link: Rc<dyn ContextNodeT>,
+    pub tree_path: String,
     children_mount: Option<Box<Mount<VNativeNode>>>,
     pub native_mount_factory: Rc<dyn NativeMountFactory<VNativeNode>>,
 }
 
 impl<VNativeNode> ContextMount<VNativeNode> {
-    pub fn new(c: Box<dyn VContextT<VNativeNode>>, context_link: ContextLink, native_mount_factory: Rc<dyn NativeMountFactory<VNativeNode>>, updater: Rc<RefCell<Updater>>) -> ContextMount<VNativeNode> {
+    pub fn new(c: Box<dyn VContextT<VNativeNode>>, context_link: ContextLink, tree_path: String, native_mount_factory: Rc<dyn NativeMountFactory<VNativeNode>>, updater: Rc<RefCell<Updater>>) -> ContextMount<VNativeNode> {
         let (context_link, children) = c.to_context_link(context_link);
         let mut result = ContextMount {
             updater,
             native_mount_factory: native_mount_factory.component_native_mount_factory(),
             context_link,
+            tree_path,
             children_mount: None
         };
 
@@ -30,7 +32,7 @@ impl<VNativeNode> ContextMount<VNativeNode> {
         self.children_mount = Some(Box::new(if let Some(children_mount) = self.children_mount.take() {
             children_mount.update(children, self.native_mount_factory.clone(), self.updater.clone())
         } else {
-            Mount::new(children, Some(self.context_link.clone()), self.native_mount_factory.clone(), self.updater.clone())
+            Mount::new(children, Some(self.context_link.clone()), child_tree_path(&self.tree_path, "0"), self.native_mount_factory.clone(), self.updater.clone())
         }));
     }
 
@@ -1,25 +1,29 @@
 use crate::v_node::{VNode};
-use crate::scope::{Updater, ContextLink, clone_context_link};
+use crate::scope::{Updater, ContextLink, clone_context_link, is_strict, warn};
 use std::rc::Rc;
 use std::cell::{RefCell};
 use std::collections::HashMap;
 use crate::renderer::native::NativeMountFactory;
-use crate::renderer::mount::Mount;
+use crate::renderer::mount::{Mount, child_tree_path};
 
 pub struct FragmentMount<VNativeNode: 'static> {
     updater: Rc<RefCell<Updater>>,
     content: Vec<(String, Mount<VNativeNode>)>,
     pub context_link: ContextLink,
-    native_mount_factory: Rc<dyn NativeMountFactory<VNativeNode>>
+    pub tree_path: String,
+    native_mount_factory: Rc<dyn NativeMountFactory<VNativeNode>>,
+    strict: bool
 }
 
 impl<VNativeNode: 'static> FragmentMount<VNativeNode> {
-    pub fn new(fragment: Vec<(String, VNode<VNativeNode>)>, context_link: ContextLink, native_mount_factory: Rc<dyn NativeMountFactory<VNativeNode>>, updater: Rc<RefCell<Updater>>) -> FragmentMount<VNativeNode> {
+    pub fn new(fragment: Vec<(String, VNode<VNativeNode>)>, context_link: ContextLink, tree_path: String, native_mount_factory: Rc<dyn NativeMountFactory<VNativeNode>>, updater: Rc<RefCell<Updater>>) -> FragmentMount<VNativeNode> {
         let mut renderer = FragmentMount {
+            strict: is_strict(&context_link),
             updater,
             content: vec![],
             native_mount_factory,
-            context_link
+            context_link,
+            tree_path
         };
 
         renderer.rerender(fragment);
@@ -27,7 +31,10 @@ impl<VNativeNode: 'static> FragmentMount<VNativeNode> {
         renderer
     }
 
-    fn rerender(&mut self, fragment: Vec<(String, VNode<VNativeNode>)>) -> () {
+    fn rerender(&mut self, fragment: Vec<(String, VNode<VNativeNode>)>) {
+        if self.strict && !self.content.is_empty() && self.content.len() != fragment.len() && is_index_keyed(&fragment) {
+            warn(&format!("list at `{}` changed length from {} to {} but is keyed by index; give dynamic lists stable keys", self.tree_path, self.content.len(), fragment.len()));
+        }
         let mut map = HashMap::new();
         map.reserve(self.content.len());
         let content = std::mem::take(&mut self.content);
@@ -39,7 +46,8 @@ impl<VNativeNode: 'static> FragmentMount<VNativeNode> {
                 let old_mount = map.remove(&key).unwrap();
                 (key, old_mount.update(node, self.native_mount_factory.clone(), self.updater.clone()))
             } else {
-                (key, Mount::new(node, clone_context_link(&self.context_link), self.native_mount_factory.clone(), self.updater.clone()))
+                let tree_path = child_tree_path(&self.tree_path, &key);
+                (key, Mount::new(node, clone_context_link(&self.context_link), tree_path, self.native_mount_factory.clone(), self.updater.clone()))
             }
         }).collect();
         for (_, mut old_mount) in map.into_iter() {
@@ -48,14 +56,20 @@ impl<VNativeNode: 'static> FragmentMount<VNativeNode> {
     }
 
 
-    pub fn unmount(&mut self) -> () {
+    pub fn unmount(&mut self) {
         let content = std::mem::take(&mut self.content);
         for (_, mut old_mount) in content.into_iter() {
             old_mount.unmount();
         }
     }
 
-    pub fn update(&mut self, fragment: Vec<(String, VNode<VNativeNode>)>) -> () {
+    pub fn update(&mut self, fragment: Vec<(String, VNode<VNativeNode>)>) {
         self.rerender(fragment)
     }
+}
+
+fn is_index_keyed<VNativeNode>(fragment: &[(String, VNode<VNativeNode>)]) -> bool {
+    fragment.iter().enumerate().all(|(index, (key, _))| {
+        *key == index.to_string()
+    })
 }
\ No newline at end of file
@@ -3,7 +3,9 @@ mod fragment;
 mod context;
 mod component;
 mod mount;
+#[cfg(test)]
+pub mod test_native;
 
 pub use crate::renderer::native::{NativeMount, NativeMountFactory};
 pub use crate::renderer::component::ComponentMount;
-pub use crate::renderer::mount::Mount;
\ No newline at end of file
+pub use crate::renderer::mount::{Mount, child_tree_path};
\ No newline at end of file
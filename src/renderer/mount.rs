@@ -1,5 +1,5 @@
 use crate::v_node::{VNode};
-use crate::scope::{Updater, ContextLink, clone_context_link, Renderer, update};
+use crate::scope::{Updater, ContextLink, clone_context_link};
 use std::rc::Rc;
 use std::cell::{RefCell};
 use crate::renderer::component::ComponentMount;
@@ -14,18 +14,28 @@ pub enum Mount<VNativeNode: 'static> {
     Context(ContextMount<VNativeNode>),
 }
 
+// Tree paths end up in element ids, so keys are escaped: anything but an
+// ASCII letter or digit is written as `_<hex code>_`. That keeps paths valid
+// in `id`, `for` and `aria-labelledby`, and distinct keys distinct.
+pub fn child_tree_path(parent: &str, segment: &str) -> String {
+    let mut path = format!("{}-", parent);
+    for c in segment.chars() {
+        if c.is_ascii_alphanumeric() {
+            path.push(c);
+        } else {
+            path.push_str(&format!("_{:x}_", c as u32));
+        }
+    }
+    path
+}
+
 impl<VNativeNode: 'static> Mount<VNativeNode> {
-    pub fn new(vnode: VNode<VNativeNode>, context_link: ContextLink, native_mount_factory: Rc<dyn NativeMountFactory<VNativeNode>>, updater: Rc<RefCell<Updater>>) -> Mount<VNativeNode> {
+    pub fn new(vnode: VNode<VNativeNode>, context_link: ContextLink, tree_path: String, native_mount_factory: Rc<dyn NativeMountFactory<VNativeNode>>, updater: Rc<RefCell<Updater>>) -> Mount<VNativeNode> {
         match vnode {
-            VNode::Native(native) => Mount::Native(native_mount_factory.make_native_mount(native, context_link, updater)),
-            VNode::Fragment(fragment) => Mount::Fragment(FragmentMount::new(fragment, context_link, native_mount_factory, updater)),
-            VNode::Component(component) => Mount::Component({
-                let renderer = ComponentMount::new(component, context_link, native_mount_factory, updater);
-                let r: Rc<RefCell<dyn Renderer>> = renderer.clone();
-                update(&r, |_|{});
-                renderer
-            }),
-            VNode::Context(context) => Mount::Context(ContextMount::new(context, context_link, native_mount_factory, updater))
+            VNode::Native(native) => Mount::Native(native_mount_factory.make_native_mount(native, context_link, tree_path, updater)),
+            VNode::Fragment(fragment) => Mount::Fragment(FragmentMount::new(fragment, context_link, tree_path, native_mount_factory, updater)),
+            VNode::Component(component) => Mount::Component(ComponentMount::new(component, context_link, tree_path, native_mount_factory, updater)),
+            VNode::Context(context) => Mount::Context(ContextMount::new(context, context_link, tree_path, native_mount_factory, updater))
         }
     }
 
@@ -56,8 +66,9 @@ impl<VNativeNode: 'static> Mount<VNativeNode> {
             }
             (mut m, vnode) => {
                 let context_link = m.get_context_link();
+                let tree_path = m.get_tree_path();
                 m.unmount();
-                Mount::new(vnode, context_link, parent_native_mount_factory, updater)
+                Mount::new(vnode, context_link, tree_path, parent_native_mount_factory, updater)
             }
         }
     }
@@ -71,6 +82,15 @@ impl<VNativeNode: 'static> Mount<VNativeNode> {
         }
     }
 
+    pub fn get_tree_path(&self) -> String {
+        match self {
+            Mount::Native(native) => native.try_borrow().unwrap().get_tree_path().to_string(),
+            Mount::Fragment(fragment) => fragment.tree_path.clone(),
+            Mount::Component(component) => component.try_borrow().unwrap().tree_path.clone(),
+            Mount::Context(context) => context.tree_path.clone()
+        }
+    }
+
     pub fn unmount(&mut self) {
         match self {
             Mount::Native(native) => native.try_borrow_mut().unwrap().unmount(),
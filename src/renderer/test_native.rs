@@ -0,0 +1,101 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use crate::scope::{Updater, ContextLink, clone_context_link, flush};
+use crate::v_node::{ComponentModel, VComponentElement};
+use crate::renderer::native::{NativeMount, NativeMountFactory};
+use crate::renderer::component::ComponentMount;
+
+// Native side for native unit tests. Nodes are plain labels and mounting
+// them changes nothing, so tests only observe components, hooks and the
+// updater.
+pub struct TestMountFactory;
+
+struct TestMount {
+    context_link: ContextLink,
+    tree_path: String
+}
+
+impl NativeMount<String> for TestMount {
+    fn get_context_link(&self) -> &ContextLink {
+        &self.context_link
+    }
+
+    fn get_tree_path(&self) -> &str {
+        &self.tree_path
+    }
+
+    fn update(&mut self, _new_element: String, _native_mount_factory: Rc<dyn NativeMountFactory<String>>, _updater: Rc<RefCell<Updater>>) {
+    }
+
+    fn unmount(&mut self) {
+    }
+}
+
+impl NativeMountFactory<String> for TestMountFactory {
+    fn make_native_mount(self: Rc<Self>, _native_node: String, context_link: ContextLink, tree_path: String, _updater: Rc<RefCell<Updater>>) -> Rc<RefCell<dyn NativeMount<String>>> {
+        Rc::new(RefCell::new(TestMount {
+            context_link: clone_context_link(&context_link),
+            tree_path
+        }))
+    }
+
+    fn component_native_mount_factory(self: Rc<Self>) -> Rc<dyn NativeMountFactory<String>> {
+        Rc::new(TestMountFactory)
+    }
+
+    fn commit(&self) {
+    }
+
+    fn reset_scanner(&self) {
+    }
+
+    fn maybe_update_native_mount_sequence(&self, _mount: Rc<RefCell<dyn NativeMount<String>>>) {
+    }
+
+    fn maybe_update_component_mount_sequence(&self, _mount: Rc<dyn NativeMountFactory<String>>) {
+    }
+
+    fn on_unmount(self: Rc<Self>) {
+    }
+}
+
+// Shared record of what components and effects did, in order. Compares by
+// identity so that passing it as a prop does not force re-renders.
+#[derive(Clone)]
+pub struct Log(Rc<RefCell<Vec<String>>>);
+
+impl PartialEq for Log {
+    fn eq(&self, other: &Log) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Log {
+    pub fn new() -> Log {
+        Log(Rc::new(RefCell::new(vec![])))
+    }
+
+    pub fn push<S: Into<String>>(&self, entry: S) {
+        self.0.try_borrow_mut().unwrap().push(entry.into());
+    }
+
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.0.try_borrow_mut().unwrap())
+    }
+}
+
+pub fn mount_root<Model: ComponentModel<String, ()> + 'static>(model: Model, updater: &Rc<RefCell<Updater>>) -> Rc<RefCell<ComponentMount<String>>> {
+    let root = ComponentMount::new(Box::new(VComponentElement::new(model, None)), None, String::from("r"), Rc::new(TestMountFactory), updater.clone());
+    flush(updater);
+    root
+}
+
+pub fn update_root<Model: ComponentModel<String, ()> + 'static>(root: &Rc<RefCell<ComponentMount<String>>>, model: Model, updater: &Rc<RefCell<Updater>>) {
+    root.try_borrow_mut().unwrap().update(Box::new(VComponentElement::new(model, None)));
+    flush(updater);
+}
+
+pub fn unmount_root(root: &Rc<RefCell<ComponentMount<String>>>, updater: &Rc<RefCell<Updater>>) {
+    root.try_borrow_mut().unwrap().unmount();
+    flush(updater);
+}
@@ -1,12 +1,14 @@
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use std::cell::RefCell;
 use crate::scope::renderer::Renderer;
 use crate::scope::scope::Scope;
-use crate::scope::updater::update;
+use crate::scope::updater::update_if_mounted;
+
+pub type CallbackFunc<T> = Rc<dyn Fn(&mut Scope, T)>;
 
 pub struct CallbackHandle<T> {
-    pub func: Rc<dyn Fn(&mut Scope, T) -> ()>,
-    pub renderer: Rc<RefCell<dyn Renderer>>,
+    pub func: CallbackFunc<T>,
+    pub renderer: Weak<RefCell<dyn Renderer>>,
 }
 
 impl<T> Clone for CallbackHandle<T> {
@@ -20,7 +22,7 @@ impl<T> Clone for CallbackHandle<T> {
 
 impl<T> PartialEq for CallbackHandle<T> {
     fn eq(&self, other: &CallbackHandle<T>) -> bool {
-        Rc::ptr_eq(&self.func, &other.func) && Rc::ptr_eq(&self.renderer, &other.renderer)
+        Rc::ptr_eq(&self.func, &other.func) && self.renderer.ptr_eq(&other.renderer)
     }
 }
 
@@ -28,7 +30,7 @@ impl<T> PartialEq for CallbackHandle<T> {
 impl<T: 'static> CallbackHandle<T> {
     pub fn trigger(&self, arg: T) {
         let func = self.func.clone();
-        update(&self.renderer, move |scope| {
+        update_if_mounted(&self.renderer, move |scope| {
             func(scope, arg)
         });
     }
@@ -1,46 +1,176 @@
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use std::cell::RefCell;
 use downcast_rs::Downcast;
 use crate::scope::renderer::Renderer;
 use crate::scope::updater::update;
 
 
+pub struct Context<T: 'static> {
+    default: fn() -> T,
+    phantom: std::marker::PhantomData<fn() -> T>
+}
+
+impl<T: 'static> Context<T> {
+    pub const fn new(default: fn() -> T) -> Context<T> {
+        Context {
+            default,
+            phantom: std::marker::PhantomData
+        }
+    }
+
+    pub fn key(&self) -> usize {
+        self as *const Context<T> as usize
+    }
+
+    pub fn default_value(&self) -> T {
+        (self.default)()
+    }
+}
+
 pub struct ContextConsumerHandle<T: 'static> {
-    pub context_node: Rc<ContextNode<T>>,
+    pub context_node: Option<Rc<ContextNode<T>>>,
+    pub default_value: Rc<T>,
+}
+
+impl<T: 'static> ContextConsumerHandle<T> {
+    pub fn value(&self) -> Rc<T> {
+        match self.context_node.as_ref() {
+            Some(context_node) => context_node.value.try_borrow().unwrap().clone(),
+            None => self.default_value.clone()
+        }
+    }
 }
 
 pub trait ContextConsumerHandleT: Downcast {
-    fn cleanup(&self, renderer: Rc<RefCell<dyn Renderer>>);
+    fn cleanup(&self, renderer: &Weak<RefCell<dyn Renderer>>);
 }
 impl_downcast!(ContextConsumerHandleT);
 
 impl<T: 'static> ContextConsumerHandleT for ContextConsumerHandle<T> {
-    fn cleanup(&self, renderer: Rc<RefCell<dyn Renderer>>) {
-        let mut renderers = self.context_node.renderers.try_borrow_mut().unwrap();
-        let index = renderers.iter().position(|r| {
-            r.as_ptr() == renderer.as_ptr()
-        }).unwrap();
-        renderers.remove(index);
+    fn cleanup(&self, renderer: &Weak<RefCell<dyn Renderer>>) {
+        if let Some(context_node) = self.context_node.as_ref() {
+            context_node.unsubscribe(renderer);
+        }
+    }
+}
+
+pub type Selector<T, S> = Rc<dyn Fn(&T) -> S>;
+
+pub struct ContextSelectorHandle<T: 'static, S: Clone + PartialEq + 'static> {
+    pub context_node: Option<Rc<ContextNode<T>>>,
+    pub default_value: Rc<T>,
+    pub selector: Rc<RefCell<Selector<T, S>>>,
+    pub selected: Rc<RefCell<S>>,
+}
+
+impl<T: 'static, S: Clone + PartialEq + 'static> ContextSelectorHandle<T, S> {
+    pub fn new(context_node: Option<Rc<ContextNode<T>>>, default_value: Rc<T>, selector: Selector<T, S>) -> ContextSelectorHandle<T, S> {
+        let value = match context_node.as_ref() {
+            Some(context_node) => context_node.value.try_borrow().unwrap().clone(),
+            None => default_value.clone()
+        };
+        let selected = selector(&value);
+        ContextSelectorHandle {
+            context_node,
+            default_value,
+            selector: Rc::new(RefCell::new(selector)),
+            selected: Rc::new(RefCell::new(selected))
+        }
+    }
+
+    pub fn has_changed(&self) -> Rc<dyn Fn(&T) -> bool> {
+        let selector = self.selector.clone();
+        let selected = self.selected.clone();
+        Rc::new(move |value| {
+            let selector = selector.try_borrow().unwrap().clone();
+            selector(value) != *selected.try_borrow().unwrap()
+        })
+    }
+
+    pub fn select(&self, selector: Selector<T, S>) -> S {
+        let value = match self.context_node.as_ref() {
+            Some(context_node) => context_node.value.try_borrow().unwrap().clone(),
+            None => self.default_value.clone()
+        };
+        let selected = selector(&value);
+        *self.selector.try_borrow_mut().unwrap() = selector;
+        *self.selected.try_borrow_mut().unwrap() = selected.clone();
+        selected
+    }
+}
+
+impl<T: 'static, S: Clone + PartialEq + 'static> ContextConsumerHandleT for ContextSelectorHandle<T, S> {
+    fn cleanup(&self, renderer: &Weak<RefCell<dyn Renderer>>) {
+        if let Some(context_node) = self.context_node.as_ref() {
+            context_node.unsubscribe(renderer);
+        }
     }
 }
 
 pub type ContextLink = Option<Rc<dyn ContextNodeT>>;
 
+// Tells whether a new value matters to a subscriber; subscribers without one
+// are updated on every change.
+pub type ChangeFilter<T> = Rc<dyn Fn(&T) -> bool>;
+
+pub struct ContextSubscription<T> {
+    pub renderer: Weak<RefCell<dyn Renderer>>,
+    pub has_changed: Option<ChangeFilter<T>>,
+}
+
 pub struct ContextNode<T> {
+    pub key: usize,
     pub parent: ContextLink,
     pub value: RefCell<Rc<T>>,
-    pub renderers: RefCell<Vec<Rc<RefCell<dyn Renderer>>>>
+    pub subscriptions: RefCell<Vec<ContextSubscription<T>>>
+}
+
+impl<T> ContextNode<T> {
+    pub fn subscribe(&self, renderer: Weak<RefCell<dyn Renderer>>, has_changed: Option<ChangeFilter<T>>) {
+        let mut subscriptions = self.subscriptions.try_borrow_mut().unwrap();
+        subscriptions.retain(|s| s.renderer.strong_count() > 0);
+        subscriptions.push(ContextSubscription {
+            renderer,
+            has_changed
+        });
+    }
+
+    pub fn unsubscribe(&self, renderer: &Weak<RefCell<dyn Renderer>>) {
+        let mut subscriptions = self.subscriptions.try_borrow_mut().unwrap();
+        if let Some(index) = subscriptions.iter().position(|s| {
+            s.renderer.ptr_eq(renderer)
+        }) {
+            subscriptions.remove(index);
+        }
+        subscriptions.retain(|s| s.renderer.strong_count() > 0);
+    }
+
+    pub fn subscription_count(&self) -> usize {
+        self.subscriptions.try_borrow().unwrap().len()
+    }
 }
 
 pub trait ContextNodeT: Downcast {
     fn trigger_update(&self);
     fn parent(&self) -> &ContextLink;
+    fn key(&self) -> usize;
 }
 impl_downcast!(ContextNodeT);
 
 impl<T: 'static> ContextNodeT for ContextNode<T> {
     fn trigger_update(&self) {
-        for r in self.renderers.try_borrow().unwrap().iter() {
+        let value = self.value.try_borrow().unwrap().clone();
+        let renderers: Vec<Rc<RefCell<dyn Renderer>>> = {
+            let mut subscriptions = self.subscriptions.try_borrow_mut().unwrap();
+            subscriptions.retain(|s| s.renderer.strong_count() > 0);
+            subscriptions.iter().filter(|s| {
+                match s.has_changed.as_ref() {
+                    Some(has_changed) => has_changed(&value),
+                    None => true
+                }
+            }).filter_map(|s| s.renderer.upgrade()).collect()
+        };
+        for r in renderers.iter() {
             update(r, |scope| {
                 scope.mark_update();
             });
@@ -50,9 +180,24 @@ impl<T: 'static> ContextNodeT for ContextNode<T> {
     fn parent(&self) -> &ContextLink {
         &self.parent
     }
+
+    fn key(&self) -> usize {
+        self.key
+    }
+}
+
+pub fn find_context_node<T: 'static>(context_link: &ContextLink, context: &Context<T>) -> Option<Rc<ContextNode<T>>> {
+    let mut current = clone_context_link(context_link);
+    while let Some(node) = current {
+        if node.key() == context.key() {
+            return node.downcast_rc::<ContextNode<T>>().ok();
+        }
+        current = clone_context_link(node.parent());
+    }
+    None
 }
 
 pub fn clone_context_link(context_link: &ContextLink) -> ContextLink {
-    return context_link.as_ref().map(|l|{l.clone()})
+    context_link.as_ref().map(|l|{l.clone()})
 }
 
@@ -1,21 +1,84 @@
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use std::cell::{RefCell, Cell};
 use downcast_rs::Downcast;
+use crate::scope::renderer::Renderer;
+use crate::scope::state::{StateHandle, StateSetter};
+use crate::scope::future::{LocalFuture, TaskHandle, spawn_cancellable};
+use crate::scope::updater::update_if_mounted;
+use crate::scope::timer::Clock;
 
-pub struct EffectStore<Basis: Eq, F: Fn() -> Option<C>, C: FnOnce() -> ()> {
+pub struct EffectHandle {
+    renderer: Weak<RefCell<dyn Renderer>>,
+}
+
+impl Clone for EffectHandle {
+    fn clone(&self) -> EffectHandle {
+        EffectHandle {
+            renderer: self.renderer.clone()
+        }
+    }
+}
+
+impl EffectHandle {
+    pub fn new(renderer: &Weak<RefCell<dyn Renderer>>) -> EffectHandle {
+        EffectHandle {
+            renderer: renderer.clone()
+        }
+    }
+
+    pub fn update_state<T: Clone + PartialEq + 'static>(&self, handle: StateHandle<T>, new_value: T) {
+        self.setter(handle).set(new_value)
+    }
+
+    pub fn update_state_map<T: Clone + PartialEq + 'static, F: FnOnce(&T) -> T + 'static>(&self, handle: StateHandle<T>, mapper: F) {
+        self.setter(handle).set_map(mapper)
+    }
+
+    pub fn setter<T: Clone + PartialEq + 'static>(&self, handle: StateHandle<T>) -> StateSetter<T> {
+        StateSetter {
+            handle,
+            renderer: self.renderer.clone()
+        }
+    }
+
+    pub fn clock(&self) -> Rc<dyn Clock> {
+        let renderer = self.renderer.upgrade().expect("clock requested for a component that no longer exists");
+        let clock = renderer.try_borrow().unwrap().updater().try_borrow().unwrap().clock();
+        clock
+    }
+
+    pub fn request_update(&self) {
+        update_if_mounted(&self.renderer, |scope| {
+            scope.mark_update();
+        });
+    }
+
+    pub fn spawn(&self, future: LocalFuture) -> TaskHandle {
+        let renderer = self.renderer.upgrade().expect("spawn called for a component that no longer exists");
+        let executor = renderer.try_borrow().unwrap().updater().try_borrow().unwrap().executor();
+        spawn_cancellable(executor.as_ref(), future)
+    }
+}
+
+pub struct EffectStore<Basis: Eq, F: Fn(&EffectHandle) -> Option<C>, C: FnOnce()> {
     pub effect: F,
     pub cleanup: Rc<RefCell<Option<C>>>,
     pub basis: Basis,
-    pub pending_execution: Cell<bool>
+    pub pending_execution: Cell<bool>,
+    pub handle: EffectHandle
 }
 
-impl<Basis: Eq, F: Fn() -> Option<C>, C: FnOnce() -> ()> EffectStore<Basis, F, C> {
+impl<Basis: Eq, F: Fn(&EffectHandle) -> Option<C>, C: FnOnce()> EffectStore<Basis, F, C> {
     pub fn update(&self, new_effect: F, new_basis: Basis) -> Self {
+        // The replaced store may still sit in the effect queue; only the
+        // newest store for a hook is allowed to run.
+        self.pending_execution.replace(false);
         EffectStore {
             effect: new_effect,
             basis: new_basis,
             cleanup: self.cleanup.clone(),
-            pending_execution: Cell::new(true)
+            pending_execution: Cell::new(true),
+            handle: self.handle.clone()
         }
     }
 }
@@ -23,14 +86,15 @@ impl<Basis: Eq, F: Fn() -> Option<C>, C: FnOnce() -> ()> EffectStore<Basis, F, C
 pub trait EffectStoreT: Downcast {
     fn execute(&self);
     fn cleanup(&self);
+    fn cancel(&self);
     fn is_pending(&self) -> bool;
 }
 impl_downcast!(EffectStoreT);
 
-impl<T: Eq + 'static, F: Fn() -> Option<C> + 'static, C: FnOnce() -> () + 'static> EffectStoreT for EffectStore<T, F, C> {
+impl<T: Eq + 'static, F: Fn(&EffectHandle) -> Option<C> + 'static, C: FnOnce() + 'static> EffectStoreT for EffectStore<T, F, C> {
     fn execute(&self) {
         self.cleanup();
-        *self.cleanup.borrow_mut() = (self.effect)();
+        *self.cleanup.borrow_mut() = (self.effect)(&self.handle);
         self.pending_execution.replace(false);
     }
     fn cleanup(&self) {
@@ -39,6 +103,10 @@ impl<T: Eq + 'static, F: Fn() -> Option<C> + 'static, C: FnOnce() -> () + 'stati
         }
     }
 
+    fn cancel(&self) {
+        self.pending_execution.replace(false);
+    }
+
     fn is_pending(&self) -> bool {
         self.pending_execution.get()
     }
@@ -52,6 +120,10 @@ impl EffectStoreT for () {
         panic!("Should not")
     }
 
+    fn cancel(&self) {
+        panic!("Should not")
+    }
+
     fn is_pending(&self) -> bool {
         panic!("Should not")
     }
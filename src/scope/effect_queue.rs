@@ -0,0 +1,84 @@
+use std::rc::Rc;
+use crate::scope::effect::EffectStoreT;
+
+// The effect, whether strict mode should re-mount it, and the depth of the
+// component it belongs to.
+pub type QueuedEffect = (Rc<dyn EffectStoreT>, bool, usize);
+
+// Cleanup of an effect or a custom hook of an unmounted component.
+pub type UnmountCleanup = Box<dyn FnOnce()>;
+
+// Effects are queued when their component finishes rendering. Time sliced
+// roots may render a child after its parent, so the queue is handed out
+// deepest first to keep children's effects before their parent's. Cleanups
+// of unmounted components wait here too, so they run after the commit.
+pub struct EffectQueue {
+    layout_effects: Vec<QueuedEffect>,
+    passive_effects: Vec<QueuedEffect>,
+    unmount_cleanups: Vec<UnmountCleanup>
+}
+
+impl EffectQueue {
+    pub fn new() -> EffectQueue {
+        EffectQueue {
+            layout_effects: vec![],
+            passive_effects: vec![],
+            unmount_cleanups: vec![]
+        }
+    }
+
+    pub fn push_layout(&mut self, effect: QueuedEffect) {
+        self.layout_effects.push(effect);
+    }
+
+    pub fn push_passive(&mut self, effect: QueuedEffect) {
+        self.passive_effects.push(effect);
+    }
+
+    pub fn push_unmount_cleanups(&mut self, cleanups: Vec<UnmountCleanup>) {
+        self.unmount_cleanups.extend(cleanups);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layout_effects.is_empty() && self.passive_effects.is_empty() && self.unmount_cleanups.is_empty()
+    }
+
+    pub fn take_layout(&mut self) -> Vec<QueuedEffect> {
+        children_first(std::mem::take(&mut self.layout_effects))
+    }
+
+    pub fn take_passive(&mut self) -> Vec<QueuedEffect> {
+        children_first(std::mem::take(&mut self.passive_effects))
+    }
+
+    pub fn take_unmount_cleanups(&mut self) -> Vec<UnmountCleanup> {
+        std::mem::take(&mut self.unmount_cleanups)
+    }
+}
+
+fn children_first(mut effects: Vec<QueuedEffect>) -> Vec<QueuedEffect> {
+    effects.sort_by_key(|(_, _, depth)| std::cmp::Reverse(*depth));
+    effects
+}
+
+// Runs every cleanup of the batch before any effect body. Effects that were
+// superseded by a later render or cancelled by an unmount since being queued
+// are no longer pending and are skipped.
+pub fn run_effects(effects: Vec<QueuedEffect>) {
+    for (effect, _, _) in effects.iter() {
+        if effect.is_pending() {
+            effect.cleanup();
+        }
+    }
+    for (effect, remount, _) in effects.iter() {
+        if effect.is_pending() {
+            effect.execute();
+            // Strict mode mounts, cleans up and re-mounts effects on first
+            // commit so that effects with missing cleanups are noticed early.
+            if *remount {
+                effect.cleanup();
+                effect.execute();
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll, Waker, Wake};
+
+pub type LocalFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+#[derive(Clone, PartialEq)]
+pub enum FutureStatus<T, E> {
+    Pending,
+    Ready(T),
+    Error(E)
+}
+
+pub trait Executor {
+    fn spawn(&self, future: LocalFuture);
+}
+
+struct TaskSlot {
+    future: Option<LocalFuture>,
+    waker: Option<Waker>,
+    cancelled: bool
+}
+
+pub struct TaskHandle {
+    slot: Rc<RefCell<TaskSlot>>
+}
+
+impl TaskHandle {
+    pub fn cancel(&self) {
+        let waker = {
+            let mut slot = self.slot.try_borrow_mut().unwrap();
+            slot.cancelled = true;
+            slot.future = None;
+            slot.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+struct CancellableFuture {
+    slot: Rc<RefCell<TaskSlot>>
+}
+
+impl Future for CancellableFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut future = match self.slot.try_borrow_mut().unwrap().future.take() {
+            Some(future) => future,
+            None => return Poll::Ready(())
+        };
+        let result = future.as_mut().poll(cx);
+        let mut slot = self.slot.try_borrow_mut().unwrap();
+        if result.is_pending() && !slot.cancelled {
+            slot.future = Some(future);
+            slot.waker = Some(cx.waker().clone());
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
+
+pub fn spawn_cancellable(executor: &dyn Executor, future: LocalFuture) -> TaskHandle {
+    let slot = Rc::new(RefCell::new(TaskSlot {
+        future: Some(future),
+        waker: None,
+        cancelled: false
+    }));
+    executor.spawn(Box::pin(CancellableFuture {
+        slot: slot.clone()
+    }));
+    TaskHandle {
+        slot
+    }
+}
+
+struct LocalTaskWaker {
+    woken: AtomicBool
+}
+
+impl Wake for LocalTaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.woken.store(true, Ordering::SeqCst);
+    }
+}
+
+// Single-threaded executor that only makes progress when asked to, so native
+// code without a browser event loop can drive futures step by step.
+pub struct LocalExecutor {
+    tasks: RefCell<Vec<(LocalFuture, Arc<LocalTaskWaker>)>>
+}
+
+impl Default for LocalExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LocalExecutor {
+    pub fn new() -> LocalExecutor {
+        LocalExecutor {
+            tasks: RefCell::new(vec![])
+        }
+    }
+
+    pub fn run_until_stalled(&self) {
+        loop {
+            let tasks = std::mem::take(&mut *self.tasks.try_borrow_mut().unwrap());
+            if !tasks.iter().any(|(_, waker)| waker.woken.load(Ordering::SeqCst)) {
+                self.tasks.try_borrow_mut().unwrap().extend(tasks);
+                return;
+            }
+            let mut remaining = vec![];
+            for (mut future, task_waker) in tasks.into_iter() {
+                if !task_waker.woken.swap(false, Ordering::SeqCst) {
+                    remaining.push((future, task_waker));
+                    continue;
+                }
+                let waker = Waker::from(task_waker.clone());
+                let mut cx = Context::from_waker(&waker);
+                if future.as_mut().poll(&mut cx).is_pending() {
+                    remaining.push((future, task_waker));
+                }
+            }
+            self.tasks.try_borrow_mut().unwrap().extend(remaining);
+        }
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.tasks.try_borrow().unwrap().len()
+    }
+}
+
+impl Executor for LocalExecutor {
+    fn spawn(&self, future: LocalFuture) {
+        self.tasks.try_borrow_mut().unwrap().push((future, Arc::new(LocalTaskWaker {
+            woken: AtomicBool::new(true)
+        })));
+    }
+}
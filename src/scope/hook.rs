@@ -0,0 +1,24 @@
+use downcast_rs::Downcast;
+use crate::scope::effect::EffectHandle;
+
+pub trait Hook: 'static {
+    type Input;
+    type Output;
+
+    fn init(input: Self::Input, handle: EffectHandle) -> Self;
+    fn update(&mut self, input: Self::Input);
+    fn output(&self) -> Self::Output;
+    fn cleanup(&mut self) {
+    }
+}
+
+pub trait HookT: Downcast {
+    fn cleanup(&mut self);
+}
+impl_downcast!(HookT);
+
+impl<H: Hook> HookT for H {
+    fn cleanup(&mut self) {
+        Hook::cleanup(self)
+    }
+}
@@ -0,0 +1,131 @@
+struct HookRecord {
+    kind: &'static str,
+    type_name: &'static str,
+}
+
+// Remembers the kind and store type of every hook called during the first
+// render so later renders can report exactly where the call order diverged
+// instead of failing inside a downcast.
+pub struct HookTrace {
+    records: Vec<HookRecord>,
+    current_index: usize,
+}
+
+impl HookTrace {
+    pub fn new() -> HookTrace {
+        HookTrace {
+            records: vec![],
+            current_index: 0
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+        self.current_index = 0;
+    }
+
+    pub fn start(&mut self) {
+        self.current_index = 0;
+    }
+
+    pub fn record(&mut self, component_name: &'static str, has_init: bool, kind: &'static str, type_name: &'static str) {
+        let index = self.current_index;
+        self.current_index += 1;
+        if !has_init {
+            self.records.push(HookRecord {
+                kind,
+                type_name
+            });
+            return;
+        }
+        match self.records.get(index) {
+            Some(record) => if record.kind != kind || record.type_name != type_name {
+                panic!(
+                    "hook order changed in component `{}`: hook #{} was `{}` ({}) on the first render but is now `{}` ({}). Hooks must be called unconditionally and in the same order on every render.",
+                    component_name, index, record.kind, record.type_name, kind, type_name
+                );
+            }
+            None => panic!(
+                "hook order changed in component `{}`: hook #{} `{}` ({}) was not called on the first render, which only called {} hooks. Hooks must be called unconditionally and in the same order on every render.",
+                component_name, index, kind, type_name, self.records.len()
+            )
+        }
+    }
+
+    pub fn finish(&self, component_name: &'static str, has_init: bool) {
+        if has_init && self.current_index < self.records.len() {
+            let record = &self.records[self.current_index];
+            panic!(
+                "hook order changed in component `{}`: only {} hooks were called but the first render called {}, starting with hook #{} `{}` ({}). Hooks must be called unconditionally and in the same order on every render.",
+                component_name, self.current_index, self.records.len(), self.current_index, record.kind, record.type_name
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    use crate::scope::{ComponentScope, NilRef, Updater};
+    use crate::v_node::{ComponentModel, VNode};
+    use crate::renderer::test_native::{mount_root, update_root};
+
+    #[derive(PartialEq)]
+    // Hooks called after the leading `use_state`.
+    enum Hooks {
+        Ref,
+        State,
+        RefAndState,
+        Nothing
+    }
+
+    #[derive(PartialEq)]
+    struct CallsHooks {
+        hooks: Hooks
+    }
+
+    impl ComponentModel<String, ()> for CallsHooks {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            scope.use_state(0);
+            match self.hooks {
+                Hooks::Ref => {
+                    scope.use_ref::<u32>();
+                },
+                Hooks::State => {
+                    scope.use_state(0);
+                },
+                Hooks::RefAndState => {
+                    scope.use_ref::<u32>();
+                    scope.use_state(0);
+                },
+                Hooks::Nothing => {}
+            }
+            VNode::Native(String::from("calls hooks"))
+        }
+    }
+
+    fn rerender_with(hooks: Hooks) {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let root = mount_root(CallsHooks { hooks: Hooks::Ref }, &updater);
+        update_root(&root, CallsHooks { hooks }, &updater);
+    }
+
+    #[test]
+    #[should_panic(expected = "hook order changed in component")]
+    fn a_hook_of_a_different_kind_panics() {
+        rerender_with(Hooks::State);
+    }
+
+    #[test]
+    #[should_panic(expected = "hook order changed in component")]
+    fn an_extra_hook_panics() {
+        rerender_with(Hooks::RefAndState);
+    }
+
+    #[test]
+    #[should_panic(expected = "hook order changed in component")]
+    fn a_missing_hook_panics() {
+        rerender_with(Hooks::Nothing);
+    }
+}
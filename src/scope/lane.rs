@@ -0,0 +1,45 @@
+use std::cell::Cell;
+use crate::scope::updater::batch;
+
+// Priority of an update. Lower lanes render first; a component with updates
+// in several lanes is rendered once, in the most urgent of them.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Lane {
+    Urgent,
+    Transition
+}
+
+thread_local! {
+    static CURRENT_LANE: Cell<Lane> = const { Cell::new(Lane::Urgent) };
+}
+
+pub fn current_lane() -> Lane {
+    CURRENT_LANE.with(|l| l.get())
+}
+
+pub fn with_lane<R, F: FnOnce() -> R>(lane: Lane, f: F) -> R {
+    let _lane = LaneGuard(CURRENT_LANE.with(|l| l.replace(lane)));
+    f()
+}
+
+// Restores the previous lane when dropped, also when `f` panics.
+struct LaneGuard(Lane);
+
+impl Drop for LaneGuard {
+    fn drop(&mut self) {
+        CURRENT_LANE.with(|l| l.set(self.0));
+    }
+}
+
+// Updates scheduled inside `f` are low priority and batched into one
+// transition. Their state is written right away, so a transition is never
+// discarded and an urgent render of the same component already shows it; what
+// is deferred is rendering the components they dirty. That happens in slices
+// after all urgent work, so urgent updates that come in before a slice renders
+// first. Once a slice started rendering the transition, an urgent flush
+// finishes it in the same commit. With a scheduler that runs slices right
+// away, like the default `SyncScheduler`, the transition is rendered before
+// `start_transition` returns.
+pub fn start_transition<F: FnOnce()>(f: F) {
+    with_lane(Lane::Transition, || batch(f))
+}
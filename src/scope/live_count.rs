@@ -0,0 +1,75 @@
+use std::cell::Cell;
+
+// Debug-only bookkeeping so tests can assert that unmounting a tree really
+// releases its component mounts and scopes.
+thread_local! {
+    static LIVE_SCOPES: Cell<usize> = const { Cell::new(0) };
+    static LIVE_COMPONENT_MOUNTS: Cell<usize> = const { Cell::new(0) };
+}
+
+pub fn live_scope_count() -> usize {
+    LIVE_SCOPES.with(|count| count.get())
+}
+
+pub fn live_component_mount_count() -> usize {
+    LIVE_COMPONENT_MOUNTS.with(|count| count.get())
+}
+
+pub fn track_scope(delta: isize) {
+    LIVE_SCOPES.with(|count| count.set((count.get() as isize + delta) as usize));
+}
+
+pub fn track_component_mount(delta: isize) {
+    LIVE_COMPONENT_MOUNTS.with(|count| count.set((count.get() as isize + delta) as usize));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    use super::*;
+    use crate::scope::{ComponentScope, NilRef, Updater};
+    use crate::v_node::{ComponentModel, VComponentElement, VNode};
+    use crate::renderer::test_native::{mount_root, update_root, unmount_root};
+
+    #[derive(PartialEq)]
+    struct Leaf;
+
+    impl ComponentModel<String, ()> for Leaf {
+        fn render(&self, _: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            VNode::Native(String::from("leaf"))
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct Branch {
+        leaves: usize
+    }
+
+    impl ComponentModel<String, ()> for Branch {
+        fn render(&self, _: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            VNode::Fragment((0..self.leaves).map(|index| {
+                (index.to_string(), VNode::component(VComponentElement::new(Leaf, None)))
+            }).collect())
+        }
+    }
+
+    #[test]
+    fn removed_and_unmounted_components_are_released() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let mounts = live_component_mount_count();
+        let scopes = live_scope_count();
+        let root = mount_root(Branch { leaves: 3 }, &updater);
+        assert_eq!(live_component_mount_count(), mounts + 4);
+        assert_eq!(live_scope_count(), scopes + 4);
+
+        update_root(&root, Branch { leaves: 1 }, &updater);
+        assert_eq!(live_component_mount_count(), mounts + 2);
+        assert_eq!(live_scope_count(), scopes + 2);
+
+        unmount_root(&root, &updater);
+        drop(root);
+        assert_eq!(live_component_mount_count(), mounts);
+        assert_eq!(live_scope_count(), scopes);
+    }
+}
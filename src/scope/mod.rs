@@ -1,17 +1,42 @@
+#[allow(clippy::module_inception)]
 mod scope;
 mod context;
 mod renderer;
 mod updater;
 mod state;
+mod reducer;
+mod mut_state;
 mod effect;
 mod memo;
 mod callback;
 mod ref_object;
+mod future;
+mod hook;
+mod timer;
+mod strict;
+mod effect_queue;
+mod scheduler;
+mod lane;
+#[cfg(debug_assertions)]
+mod hook_trace;
+#[cfg(debug_assertions)]
+mod live_count;
 
 pub use scope::{Scope, ComponentScope};
 pub use renderer::Renderer;
-pub use context::{ContextLink, ContextNode, ContextNodeT, clone_context_link};
+pub use context::{Context, ContextLink, ContextNode, ContextNodeT, clone_context_link};
 pub use callback::CallbackHandle;
-pub use updater::{Updater, update};
-pub use state::StateHandle;
-pub use ref_object::{RefObject, NilRef};
\ No newline at end of file
+pub use effect::EffectHandle;
+pub use hook::Hook;
+pub use lane::{Lane, current_lane, with_lane, start_transition};
+pub use scheduler::{Scheduler, SyncScheduler, ManualScheduler, Deadline, NoDeadline, SliceTask};
+pub use timer::{Clock, ManualClock, TimerId};
+pub use strict::{STRICT_MODE, STRICT_RENDER_LIMIT, is_strict, start_render, end_render, warn, log_warning};
+pub use updater::{Updater, DeferredTask, update, flush, batch, enter_render, leave_render, FLUSH_ROUND_LIMIT};
+pub use state::{StateHandle, StateSetter};
+pub use reducer::DispatchHandle;
+pub use mut_state::MutStateHandle;
+pub use ref_object::{RefObject, NilRef};
+#[cfg(debug_assertions)]
+pub use live_count::{live_scope_count, live_component_mount_count, track_component_mount};
+pub use future::{FutureStatus, Executor, LocalExecutor, LocalFuture, TaskHandle};
\ No newline at end of file
@@ -0,0 +1,61 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use crate::scope::scope::Scope;
+use crate::scope::state::{StateStoreT, HookOwner};
+
+pub struct MutStateHandle<T: 'static> {
+    owner: HookOwner,
+    index: usize,
+    phantom: std::marker::PhantomData<T>
+}
+
+impl<T: 'static> PartialEq for MutStateHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+       self.owner == other.owner && self.index == other.index
+    }
+}
+
+impl<T: 'static> Copy for MutStateHandle<T> {
+
+}
+
+impl<T: 'static> Clone for MutStateHandle<T> {
+    fn clone(&self) -> MutStateHandle<T> {
+        *self
+    }
+}
+
+impl<T: 'static> MutStateHandle<T> {
+    pub fn get(&self, scope: &Scope) -> Rc<RefCell<T>> {
+        scope.assert_owner(self.owner, "MutStateHandle");
+        scope.mut_state::<T>(self.index)
+    }
+
+    // Mutates the value in place without re-rendering; call
+    // `Scope::mark_update` when the change should become visible.
+    pub fn mutate<F: FnOnce(&mut T)>(&self, scope: &mut Scope, mutator: F) {
+        mutator(&mut *self.get(scope).try_borrow_mut().unwrap());
+    }
+}
+
+pub struct MutStateStore<T: 'static> {
+    pub value: Rc<RefCell<T>>,
+    pub handle: MutStateHandle<T>
+}
+
+impl<T: 'static> MutStateStore<T> {
+    pub fn new(value: T, owner: HookOwner, index: usize) -> MutStateStore<T> {
+        MutStateStore {
+            value: Rc::new(RefCell::new(value)),
+            handle: MutStateHandle {
+                owner,
+                index,
+                phantom: std::marker::PhantomData
+            }
+        }
+    }
+}
+
+impl<T: 'static> StateStoreT for MutStateStore<T> {
+
+}
@@ -0,0 +1,57 @@
+use crate::scope::scope::Scope;
+use crate::scope::state::{StateStoreT, HookOwner};
+
+pub struct DispatchHandle<S: Clone + PartialEq + 'static, A: 'static> {
+    owner: HookOwner,
+    index: usize,
+    phantom: std::marker::PhantomData<(S, A)>
+}
+
+impl<S: Clone + PartialEq + 'static, A: 'static> PartialEq for DispatchHandle<S, A> {
+    fn eq(&self, other: &Self) -> bool {
+       self.owner == other.owner && self.index == other.index
+    }
+}
+
+impl<S: Clone + PartialEq + 'static, A: 'static> Copy for DispatchHandle<S, A> {
+
+}
+
+impl<S: Clone + PartialEq + 'static, A: 'static> Clone for DispatchHandle<S, A> {
+    fn clone(&self) -> DispatchHandle<S, A> {
+        *self
+    }
+}
+
+impl<S: Clone + PartialEq + 'static, A: 'static> DispatchHandle<S, A> {
+    pub fn dispatch(&self, scope: &mut Scope, action: A) {
+        scope.assert_owner(self.owner, "DispatchHandle");
+        scope.dispatch_action::<S, A>(self.index, action)
+    }
+}
+
+pub type Reducer<S, A> = Box<dyn Fn(&S, A) -> S>;
+
+pub struct ReducerStore<S: Clone + PartialEq + 'static, A: 'static> {
+    pub value: S,
+    pub reducer: Reducer<S, A>,
+    pub handle: DispatchHandle<S, A>
+}
+
+impl<S: Clone + PartialEq + 'static, A: 'static> ReducerStore<S, A> {
+    pub fn new(value: S, reducer: Reducer<S, A>, owner: HookOwner, index: usize) -> ReducerStore<S, A> {
+        ReducerStore {
+            value,
+            reducer,
+            handle: DispatchHandle {
+                owner,
+                index,
+                phantom: std::marker::PhantomData
+            }
+        }
+    }
+}
+
+impl<S: Clone + PartialEq + 'static, A: 'static> StateStoreT for ReducerStore<S, A> {
+
+}
\ No newline at end of file
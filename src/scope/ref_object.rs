@@ -8,6 +8,12 @@ pub struct RefObject<T> {
     inner: Rc<RefCell<Option<T>>>
 }
 
+impl<T> Default for RefObject<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> RefObject<T> {
     pub fn new() -> RefObject<T> {
         RefObject {
@@ -15,7 +21,7 @@ impl<T> RefObject<T> {
         }
     }
 
-    pub fn borrow_mut(&self) -> RefMut<Option<T>> {
+    pub fn borrow_mut(&self) -> RefMut<'_, Option<T>> {
         self.inner.try_borrow_mut().unwrap()
     }
 
@@ -30,6 +36,10 @@ impl<T> PartialEq for RefObject<T> {
    }
 }
 
+impl<T> Eq for RefObject<T> {
+
+}
+
 impl<T> Clone for RefObject<T> {
    fn clone(&self) -> Self {
         RefObject {
@@ -7,4 +7,6 @@ pub trait Renderer {
     fn maybe_update(&mut self);
     fn scope_mut(&mut self) -> &mut Scope;
     fn updater(&self) -> Rc<RefCell<Updater>>;
+    fn is_mounted(&self) -> bool;
+    fn depth(&self) -> usize;
 }
@@ -0,0 +1,100 @@
+use std::cell::RefCell;
+use crate::scope::updater::DeferredTask;
+
+pub type SliceTask = Box<dyn FnOnce(&dyn Deadline)>;
+
+pub trait Deadline {
+    fn should_yield(&self) -> bool;
+}
+
+pub struct NoDeadline;
+
+impl Deadline for NoDeadline {
+    fn should_yield(&self) -> bool {
+        false
+    }
+}
+
+// Decides when a root flushes after its first pending update. Updates made
+// before the flush runs are all rendered by it.
+pub trait Scheduler {
+    fn schedule_flush(&self, flush: DeferredTask);
+
+    // Time sliced roots schedule their work through this, one slice at a
+    // time. Schedulers without a notion of idle time run the slice unbounded.
+    fn schedule_slice(&self, slice: SliceTask) {
+        self.schedule_flush(Box::new(move || {
+            slice(&NoDeadline);
+        }));
+    }
+}
+
+// Flushes and runs slices as soon as they are scheduled, so transitions only
+// order the work of a flush after its urgent updates.
+pub struct SyncScheduler;
+
+impl Scheduler for SyncScheduler {
+    fn schedule_flush(&self, flush: DeferredTask) {
+        flush();
+    }
+}
+
+// Scheduler that holds flushes until `run_pending` is called, so native code
+// can step through renders one flush at a time.
+pub struct ManualScheduler {
+    flushes: RefCell<Vec<DeferredTask>>,
+    slices: RefCell<Vec<SliceTask>>
+}
+
+impl Default for ManualScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualScheduler {
+    pub fn new() -> ManualScheduler {
+        ManualScheduler {
+            flushes: RefCell::new(vec![]),
+            slices: RefCell::new(vec![])
+        }
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.flushes.try_borrow().unwrap().len() + self.slices.try_borrow().unwrap().len()
+    }
+
+    // Runs the slices scheduled so far, each against the given deadline, and
+    // returns whether any were run. Slices they schedule wait for the next call.
+    pub fn run_slices(&self, deadline: &dyn Deadline) -> bool {
+        let slices = std::mem::take(&mut *self.slices.try_borrow_mut().unwrap());
+        let ran = !slices.is_empty();
+        for slice in slices.into_iter() {
+            slice(deadline);
+        }
+        ran
+    }
+
+    pub fn run_pending(&self) {
+        loop {
+            let flushes = std::mem::take(&mut *self.flushes.try_borrow_mut().unwrap());
+            let ran_slices = self.run_slices(&NoDeadline);
+            if flushes.is_empty() && !ran_slices {
+                return;
+            }
+            for flush in flushes.into_iter() {
+                flush();
+            }
+        }
+    }
+}
+
+impl Scheduler for ManualScheduler {
+    fn schedule_flush(&self, flush: DeferredTask) {
+        self.flushes.try_borrow_mut().unwrap().push(flush);
+    }
+
+    fn schedule_slice(&self, slice: SliceTask) {
+        self.slices.try_borrow_mut().unwrap().push(slice);
+    }
+}
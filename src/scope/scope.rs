@@ -1,13 +1,46 @@
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use std::cell::{RefCell, Cell};
-use std::any::Any;
 use crate::scope::renderer::Renderer;
-use crate::scope::context::{ContextLink, ContextConsumerHandleT, ContextConsumerHandle, ContextNode, clone_context_link};
-use crate::scope::state::{StateStoreT, StateStore, StateHandle};
-use crate::scope::effect::{EffectStoreT, EffectStore};
+use crate::scope::context::{Context, ContextLink, ContextConsumerHandleT, ContextConsumerHandle, ContextSelectorHandle, clone_context_link, find_context_node};
+use crate::scope::state::{StateStoreT, StateStore, StateHandle, StateSetter, HookOwner};
+use crate::scope::reducer::{ReducerStore, DispatchHandle};
+use crate::scope::mut_state::{MutStateStore, MutStateHandle};
+use crate::scope::effect::{EffectStoreT, EffectStore, EffectHandle};
 use crate::scope::memo::{MemoStoreT, MemoStore};
 use crate::scope::callback::{CallbackHandle};
 use crate::scope::ref_object::{RefObject, RefObjectT};
+use crate::scope::future::FutureStatus;
+use crate::scope::hook::{Hook, HookT};
+use crate::scope::effect_queue::UnmountCleanup;
+use crate::scope::lane::{Lane, current_lane, start_transition};
+use std::future::Future;
+#[cfg(debug_assertions)]
+use crate::scope::hook_trace::HookTrace;
+#[cfg(debug_assertions)]
+use crate::scope::live_count::track_scope;
+
+// The getter of the last render and the snapshot it returned.
+type RenderedSnapshot<T> = (Rc<dyn Fn() -> T>, T);
+
+fn snapshot_changed<T: PartialEq>(rendered: &RefObject<RenderedSnapshot<T>>) -> bool {
+    let getter = match rendered.borrow_mut().as_ref() {
+        Some((getter, _)) => getter.clone(),
+        None => return false
+    };
+    let current = getter();
+    rendered.borrow_mut().as_ref().is_some_and(|(_, snapshot)| *snapshot != current)
+}
+
+thread_local! {
+    static NEXT_SCOPE_ID: Cell<usize> = const { Cell::new(0) };
+}
+
+fn next_scope_id() -> usize {
+    NEXT_SCOPE_ID.with(|id| {
+        id.set(id.get() + 1);
+        id.get()
+    })
+}
 
 pub struct HookList<Hook> {
     pub hooks: Vec<Hook>,
@@ -29,45 +62,65 @@ impl<Hook> HookList<Hook> {
 
     fn get(&mut self) -> &mut Hook {
         self.current_index += 1;
-        self.hooks.get_mut(self.current_index - 1).unwrap()
+        self.hooks.get_mut(self.current_index - 1).expect("hook called more times than on the first render")
     }
 }
 
 pub struct Scope {
     update_flag: bool,
+    strict_remount: bool,
     pub component_scope: ComponentScope
 }
 
 impl Drop for Scope {
     fn drop(&mut self) {
         self.reset();
+        #[cfg(debug_assertions)]
+        track_scope(-1);
     }
 }
 
 impl Scope {
-    pub fn new(renderer: Rc<RefCell<dyn Renderer>>, context_link: ContextLink) -> Scope {
+    pub fn new(renderer: Rc<RefCell<dyn Renderer>>, context_link: ContextLink, tree_path: String) -> Scope {
+        #[cfg(debug_assertions)]
+        track_scope(1);
         Scope {
             update_flag: false,
+            strict_remount: false,
             component_scope: ComponentScope {
-                renderer,
+                id: next_scope_id(),
+                renderer: Rc::downgrade(&renderer),
                 context_link,
                 state_hooks: HookList::new(),
                 ref_hooks: HookList::new(),
                 effect_hooks: HookList::new(),
+                layout_effect_hooks: HookList::new(),
                 memo_hooks: HookList::new(),
                 context_hooks: HookList::new(),
+                custom_hooks: HookList::new(),
+                #[cfg(debug_assertions)]
+                hook_trace: HookTrace::new(),
+                component_name: "component",
+                tree_path,
+                id_index: 0,
                 has_init: false
             }
         }
     }
 
     pub fn reset(&mut self) {
-        let mut scope = &mut self.component_scope;
+        let scope = &mut self.component_scope;
         scope.state_hooks.clear();
         scope.effect_hooks.clear();
+        scope.layout_effect_hooks.clear();
         scope.memo_hooks.clear();
         scope.context_hooks.clear();
         scope.ref_hooks.clear();
+        scope.custom_hooks.clear();
+        #[cfg(debug_assertions)]
+        scope.hook_trace.clear();
+        // Hooks are gone, so handles created before the reset must not match.
+        scope.id = next_scope_id();
         scope.has_init = false;
     }
 
@@ -83,24 +136,56 @@ impl Scope {
         self.update_flag
     }
 
-    pub fn effects_iter(&self) -> std::slice::Iter<Rc<dyn EffectStoreT>> {
+    pub fn renderer(&self) -> Weak<RefCell<dyn Renderer>> {
+        self.component_scope.renderer.clone()
+    }
+
+    pub fn assert_owner(&self, owner: HookOwner, kind: &'static str) {
+        self.component_scope.assert_owner(owner, kind);
+    }
+
+    pub fn owns(&self, owner: HookOwner) -> bool {
+        owner.scope_id == self.component_scope.id
+    }
+
+    pub fn mark_strict_remount(&mut self) {
+        self.strict_remount = true;
+    }
+
+    pub fn take_strict_remount(&mut self) -> bool {
+        std::mem::replace(&mut self.strict_remount, false)
+    }
+
+    pub fn effects_iter(&self) -> std::slice::Iter<'_, Rc<dyn EffectStoreT>> {
         self.component_scope.effect_hooks.hooks.iter()
     }
 
+    pub fn layout_effects_iter(&self) -> std::slice::Iter<'_, Rc<dyn EffectStoreT>> {
+        self.component_scope.layout_effect_hooks.hooks.iter()
+    }
+
     pub fn clone_context_link(&self) -> ContextLink {
         clone_context_link(&self.component_scope.context_link)
     }
 
-    pub fn mark_start_render(&mut self) {
-        let mut scope = &mut self.component_scope;
+    pub fn mark_start_render(&mut self, component_name: &'static str) {
+        let scope = &mut self.component_scope;
+        scope.component_name = component_name;
+        scope.id_index = 0;
+        #[cfg(debug_assertions)]
+        scope.hook_trace.start();
         scope.state_hooks.current_index = 0;
         scope.effect_hooks.current_index = 0;
+        scope.layout_effect_hooks.current_index = 0;
         scope.ref_hooks.current_index = 0;
         scope.context_hooks.current_index = 0;
         scope.memo_hooks.current_index = 0;
+        scope.custom_hooks.current_index = 0;
     }
 
     pub fn mark_end_render(&mut self) {
+        #[cfg(debug_assertions)]
+        self.component_scope.hook_trace.finish(self.component_scope.component_name, self.component_scope.has_init);
         self.component_scope.has_init = true;
     }
 
@@ -123,37 +208,102 @@ impl Scope {
         }
     }
 
-    pub fn cleanup(&mut self) {
-        let effect_hooks = std::mem::take(&mut self.component_scope.effect_hooks.hooks);
-        for e in effect_hooks.into_iter() {
-            e.cleanup();
+    pub fn dispatch_action<S: 'static + PartialEq + Clone, A: 'static>(&mut self, index: usize, action: A) {
+        let store = self.component_scope.state_hooks.hooks.get(index).unwrap().downcast_ref::<ReducerStore<S, A>>().unwrap();
+        let new_value = (store.reducer)(&store.value, action);
+        if new_value != store.value {
+            self.mark_update();
+            let mut_store = self.component_scope.state_hooks.hooks.get_mut(index).unwrap().downcast_mut::<ReducerStore<S, A>>().unwrap();
+            mut_store.value = new_value;
+        }
+    }
+
+    pub fn mut_state<T: 'static>(&self, index: usize) -> Rc<RefCell<T>> {
+        self.component_scope.state_hooks.hooks.get(index).unwrap().downcast_ref::<MutStateStore<T>>().unwrap().value.clone()
+    }
+
+    // Effects still queued will not run. Their cleanups are returned, layout
+    // effects first and custom hooks last, for the caller to run once the
+    // unmount is committed.
+    pub fn cleanup(&mut self) -> Vec<UnmountCleanup> {
+        let mut effects = std::mem::take(&mut self.component_scope.layout_effect_hooks.hooks);
+        effects.append(&mut self.component_scope.effect_hooks.hooks);
+        let mut cleanups: Vec<UnmountCleanup> = vec![];
+        for e in effects.into_iter() {
+            e.cancel();
+            cleanups.push(Box::new(move || e.cleanup()));
+        }
+        for mut h in std::mem::take(&mut self.component_scope.custom_hooks.hooks).into_iter() {
+            cleanups.push(Box::new(move || h.cleanup()));
         }
         for c in self.component_scope.context_hooks.hooks.iter() {
-            c.cleanup(self.component_scope.renderer.clone());
+            c.cleanup(&self.component_scope.renderer);
         }
+        cleanups
     }
 }
 
 pub struct ComponentScope {
-    renderer: Rc<RefCell<dyn Renderer>>,
+    id: usize,
+    renderer: Weak<RefCell<dyn Renderer>>,
     context_link: ContextLink,
     state_hooks: HookList<Box<dyn StateStoreT>>,
     ref_hooks: HookList<Box<dyn RefObjectT>>,
     context_hooks: HookList<Rc<dyn ContextConsumerHandleT>>,
     effect_hooks: HookList<Rc<dyn EffectStoreT>>,
+    layout_effect_hooks: HookList<Rc<dyn EffectStoreT>>,
     memo_hooks: HookList<Box<dyn MemoStoreT>>,
+    custom_hooks: HookList<Box<dyn HookT>>,
+    #[cfg(debug_assertions)]
+    hook_trace: HookTrace,
+    component_name: &'static str,
+    tree_path: String,
+    id_index: usize,
     has_init: bool
 }
 
 impl ComponentScope {
-    pub fn use_callback<T: 'static, F: Fn(&mut Scope, T) -> () + 'static>(&self, callback: F) -> CallbackHandle<T> {
+    fn owner(&self) -> HookOwner {
+        HookOwner {
+            scope_id: self.id,
+            component_name: self.component_name
+        }
+    }
+
+    fn assert_owner(&self, owner: HookOwner, kind: &'static str) {
+        if owner.scope_id != self.id {
+            panic!(
+                "{} created by component `{}` was used to update component `{}`. Handles only work with the scope of the component that created them; pass a CallbackHandle or a StateSetter to other components instead.",
+                kind, owner.component_name, self.component_name
+            );
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    fn track_hook<Store: 'static>(&mut self, kind: &'static str) {
+        self.hook_trace.record(self.component_name, self.has_init, kind, std::any::type_name::<Store>());
+    }
+
+    #[cfg(not(debug_assertions))]
+    fn track_hook<Store: 'static>(&mut self, _kind: &'static str) {
+    }
+
+    pub fn use_callback<T: 'static, F: Fn(&mut Scope, T) + 'static>(&self, callback: F) -> CallbackHandle<T> {
         CallbackHandle {
             func: Rc::new(callback),
             renderer: self.renderer.clone(),
         }
     }
 
-    pub fn use_callback_memo<T: 'static, Input: PartialEq + Clone + 'static, F: Fn(Input, &mut Scope, T) -> () + 'static>(&mut self, callback: F, input: Input) -> CallbackHandle<T> {
+    pub fn use_setter<T: 'static + PartialEq + Clone>(&self, handle: StateHandle<T>) -> StateSetter<T> {
+        self.assert_owner(handle.owner(), "StateHandle");
+        StateSetter {
+            handle,
+            renderer: self.renderer.clone(),
+        }
+    }
+
+    pub fn use_callback_memo<T: 'static, Input: PartialEq + Clone + 'static, F: Fn(Input, &mut Scope, T) + 'static>(&mut self, callback: F, input: Input) -> CallbackHandle<T> {
         let renderer = self.renderer.clone();
         let callback_rc = Rc::new(callback);
         self.use_memo(move |input| {
@@ -169,30 +319,104 @@ impl ComponentScope {
     }
 
     pub fn use_state<T: 'static + PartialEq + Clone>(&mut self, default_value: T) -> (T, StateHandle<T>) {
+        self.track_hook::<StateStore<T>>("use_state");
         if self.has_init {
             let store = self.state_hooks.get().downcast_ref::<StateStore<T>>().unwrap();
             (store.value.clone(), store.handle)
         } else {
-            let store = StateStore::new(default_value.clone(), self.state_hooks.hooks.len());
-            let handle = store.handle.clone();
+            let store = StateStore::new(default_value.clone(), self.owner(), self.state_hooks.hooks.len());
+            let handle = store.handle;
             self.state_hooks.hooks.push(Box::new(store));
             (default_value, handle)
         }
     }
 
-    pub fn use_context<T: 'static>(&mut self) -> Rc<T> {
+    // Like `use_state`, but returns a setter that can be kept and called from
+    // timers, futures or other components after this render has finished.
+    pub fn use_state_setter<T: 'static + PartialEq + Clone>(&mut self, default_value: T) -> (T, StateSetter<T>) {
+        let (value, handle) = self.use_state(default_value);
+        (value, self.use_setter(handle))
+    }
+
+    pub fn use_reducer<S: 'static + PartialEq + Clone, A: 'static, R: Fn(&S, A) -> S + 'static>(&mut self, reducer: R, initial: S) -> (S, DispatchHandle<S, A>) {
+        self.track_hook::<ReducerStore<S, A>>("use_reducer");
+        if self.has_init {
+            let store = self.state_hooks.get().downcast_mut::<ReducerStore<S, A>>().unwrap();
+            store.reducer = Box::new(reducer);
+            (store.value.clone(), store.handle)
+        } else {
+            let store = ReducerStore::new(initial.clone(), Box::new(reducer), self.owner(), self.state_hooks.hooks.len());
+            let handle = store.handle;
+            self.state_hooks.hooks.push(Box::new(store));
+            (initial, handle)
+        }
+    }
+
+    pub fn use_mut_state<T: 'static, F: FnOnce() -> T>(&mut self, init: F) -> (Rc<RefCell<T>>, MutStateHandle<T>) {
+        self.track_hook::<MutStateStore<T>>("use_mut_state");
+        if self.has_init {
+            let store = self.state_hooks.get().downcast_ref::<MutStateStore<T>>().unwrap();
+            (store.value.clone(), store.handle)
+        } else {
+            let store = MutStateStore::new(init(), self.owner(), self.state_hooks.hooks.len());
+            let result = (store.value.clone(), store.handle);
+            self.state_hooks.hooks.push(Box::new(store));
+            result
+        }
+    }
+
+    pub fn use_id(&mut self) -> String {
+        self.track_hook::<String>("use_id");
+        let index = self.id_index;
+        self.id_index += 1;
+        format!("{}-id{}", self.tree_path, index)
+    }
+
+    pub fn use_hook<H: Hook>(&mut self, input: H::Input) -> H::Output {
+        self.track_hook::<H>("use_hook");
+        if self.has_init {
+            let hook = self.custom_hooks.get().downcast_mut::<H>().unwrap();
+            hook.update(input);
+            hook.output()
+        } else {
+            let hook = H::init(input, EffectHandle::new(&self.renderer));
+            let output = hook.output();
+            self.custom_hooks.hooks.push(Box::new(hook));
+            output
+        }
+    }
+
+    pub fn use_context<T: 'static>(&mut self, context: &'static Context<T>) -> Rc<T> {
+        self.track_hook::<ContextConsumerHandle<T>>("use_context");
         if self.has_init {
-            self.context_hooks.get().clone().downcast_rc::<ContextConsumerHandle<T>>().ok().unwrap().context_node.value.try_borrow().unwrap().clone()
+            self.context_hooks.get().clone().downcast_rc::<ContextConsumerHandle<T>>().ok().unwrap().value()
         } else {
-            let handle = Rc::new(self.create_context_handle::<T>());
-            let result = handle.context_node.value.try_borrow().unwrap().clone();
+            let handle = Rc::new(self.create_context_handle(context));
+            let result = handle.value();
             self.context_hooks.hooks.push(handle.clone());
             result
         }
-        
+    }
+
+    pub fn use_context_selector<T: 'static, S: Clone + PartialEq + 'static, F: Fn(&T) -> S + 'static>(&mut self, context: &'static Context<T>, selector: F) -> S {
+        self.track_hook::<ContextSelectorHandle<T, S>>("use_context_selector");
+        let selector: Rc<dyn Fn(&T) -> S> = Rc::new(selector);
+        if self.has_init {
+            self.context_hooks.get().clone().downcast_rc::<ContextSelectorHandle<T, S>>().ok().unwrap().select(selector)
+        } else {
+            let context_node = find_context_node(&self.context_link, context);
+            let handle = ContextSelectorHandle::new(context_node, Rc::new(context.default_value()), selector);
+            if let Some(node) = handle.context_node.as_ref() {
+                node.subscribe(self.renderer.clone(), Some(handle.has_changed()));
+            }
+            let result = handle.selected.try_borrow().unwrap().clone();
+            self.context_hooks.hooks.push(Rc::new(handle));
+            result
+        }
     }
 
     pub fn use_ref<T: 'static>(&mut self) -> RefObject<T> {
+        self.track_hook::<RefObject<T>>("use_ref");
         if self.has_init {
             self.ref_hooks.get().downcast_ref::<RefObject<T>>().unwrap().clone()
         } else {
@@ -202,7 +426,8 @@ impl ComponentScope {
         }
     }
 
-    pub fn use_effect<Basis: Eq + 'static, C: FnOnce() -> () + 'static, F: Fn() -> Option<C> + 'static>(&mut self, effect: F, basis: Basis) {
+    pub fn use_effect<Basis: Eq + 'static, C: FnOnce() + 'static, F: Fn(&EffectHandle) -> Option<C> + 'static>(&mut self, effect: F, basis: Basis) {
+        self.track_hook::<EffectStore<Basis, F, C>>("use_effect");
         if self.has_init {
             let hook_ref = self.effect_hooks.get();
             let original_effect = hook_ref.clone().downcast_rc::<EffectStore<Basis, F, C>>().ok().unwrap();
@@ -214,12 +439,131 @@ impl ComponentScope {
                 effect,
                 cleanup: Rc::new(RefCell::new(None)),
                 basis,
-                pending_execution: Cell::new(true)
+                pending_execution: Cell::new(true),
+                handle: EffectHandle::new(&self.renderer)
+            }));
+        }
+    }
+
+    pub fn use_layout_effect<Basis: Eq + 'static, C: FnOnce() + 'static, F: Fn(&EffectHandle) -> Option<C> + 'static>(&mut self, effect: F, basis: Basis) {
+        self.track_hook::<EffectStore<Basis, F, C>>("use_layout_effect");
+        if self.has_init {
+            let hook_ref = self.layout_effect_hooks.get();
+            let original_effect = hook_ref.clone().downcast_rc::<EffectStore<Basis, F, C>>().ok().unwrap();
+            if !basis.eq(&original_effect.basis) {
+                *hook_ref = Rc::new(original_effect.update(effect, basis));
+            }
+        } else {
+            self.layout_effect_hooks.hooks.push(Rc::new(EffectStore {
+                effect,
+                cleanup: Rc::new(RefCell::new(None)),
+                basis,
+                pending_execution: Cell::new(true),
+                handle: EffectHandle::new(&self.renderer)
+            }));
+        }
+    }
+
+    pub fn use_future<Basis: Eq + Clone + 'static, T: Clone + PartialEq + 'static, E: Clone + PartialEq + 'static, Fut: Future<Output = Result<T, E>> + 'static, F: Fn(&Basis) -> Fut + 'static>(&mut self, factory: F, basis: Basis) -> FutureStatus<T, E> {
+        // The status is stored with the basis it was produced for, so a result
+        // for an old basis reads as Pending until the new future completes.
+        let (stored, stored_handle) = self.use_state::<Option<(Basis, FutureStatus<T, E>)>>(None);
+        let effect_basis = basis.clone();
+        self.use_effect(move |handle| {
+            let setter = handle.setter(stored_handle);
+            let future = factory(&effect_basis);
+            let future_basis = effect_basis.clone();
+            let task = handle.spawn(Box::pin(async move {
+                setter.set(Some((future_basis, match future.await {
+                    Ok(value) => FutureStatus::Ready(value),
+                    Err(error) => FutureStatus::Error(error)
+                })));
             }));
+            Some(move || task.cancel())
+        }, basis.clone());
+        match stored {
+            Some((stored_basis, status)) if stored_basis == basis => status,
+            _ => FutureStatus::Pending
+        }
+    }
+
+    pub fn use_imperative_handle<Ref: 'static, Basis: Eq + 'static, F: Fn() -> Ref + 'static>(&mut self, self_ref: &Option<RefObject<Ref>>, factory: F, basis: Basis) {
+        let ref_object = self_ref.clone();
+        self.use_layout_effect(move |_| {
+            ref_object.as_ref().map(|inner| {
+                inner.replace(Some(factory()));
+                let inner = inner.clone();
+                move || inner.replace(None)
+            })
+        }, (self_ref.clone(), basis));
+    }
+
+    pub fn use_interval<F: Fn(&EffectHandle) + 'static>(&mut self, callback: F, period_ms: Option<u32>) {
+        let latest_callback = self.use_ref::<Rc<dyn Fn(&EffectHandle)>>();
+        latest_callback.replace(Some(Rc::new(callback)));
+        self.use_effect(move |handle| {
+            period_ms.map(|period_ms| {
+                let clock = handle.clock();
+                let latest_callback = latest_callback.clone();
+                let handle = handle.clone();
+                let id = clock.set_interval(period_ms, Box::new(move || {
+                    let callback = latest_callback.borrow_mut().as_ref().unwrap().clone();
+                    callback(&handle);
+                }));
+                move || clock.clear(id)
+            })
+        }, period_ms);
+    }
+
+    pub fn use_timeout<F: Fn(&EffectHandle) + 'static>(&mut self, callback: F, delay_ms: Option<u32>) {
+        let latest_callback = self.use_ref::<Rc<dyn Fn(&EffectHandle)>>();
+        latest_callback.replace(Some(Rc::new(callback)));
+        self.use_effect(move |handle| {
+            delay_ms.map(|delay_ms| {
+                let clock = handle.clock();
+                let latest_callback = latest_callback.clone();
+                let handle = handle.clone();
+                let id = clock.set_timeout(delay_ms, Box::new(move || {
+                    let callback = latest_callback.borrow_mut().as_ref().unwrap().clone();
+                    callback(&handle);
+                }));
+                move || clock.clear(id)
+            })
+        }, delay_ms);
+    }
+
+    pub fn use_debounce<T: Clone + Eq + 'static>(&mut self, value: T, delay_ms: u32) -> T {
+        let (debounced, debounced_handle) = self.use_state(value.clone());
+        let pending_value = value.clone();
+        self.use_effect(move |handle| {
+            let clock = handle.clock();
+            let setter = handle.setter(debounced_handle);
+            let value = pending_value.clone();
+            let id = clock.set_timeout(delay_ms, Box::new(move || setter.set(value)));
+            Some(move || clock.clear(id))
+        }, (value, delay_ms));
+        debounced
+    }
+
+    // Lags behind `value` during urgent renders and catches up in a
+    // transition, so expensive output built from it yields to urgent updates.
+    pub fn use_deferred_value<T: Clone + Eq + 'static>(&mut self, value: T) -> T {
+        let (deferred, deferred_handle) = self.use_state(value.clone());
+        let pending_value = value.clone();
+        self.use_effect(move |handle| {
+            let setter = handle.setter(deferred_handle);
+            let value = pending_value.clone();
+            start_transition(move || setter.set(value));
+            None::<fn() -> ()>
+        }, value.clone());
+        match current_lane() {
+            Lane::Transition => value,
+            Lane::Urgent => deferred
         }
     }
 
     pub fn use_memo<F: Fn(&Input) -> Output + 'static, Input: PartialEq + 'static, Output: 'static>(&mut self, factory: F, input: Input) -> &Output {
+        self.track_hook::<MemoStore<F, Input, Output>>("use_memo");
         if self.has_init {
             let hook_ref = self.memo_hooks.get();
             let mut original_memo = std::mem::replace(hook_ref, Box::new(())).downcast::<MemoStore<F, Input, Output>>().ok().unwrap();
@@ -240,7 +584,8 @@ impl ComponentScope {
         }
     }
 
-    pub fn use_effect_always<C: FnOnce() -> () + 'static, F: Fn() -> Option<C> + Clone + 'static>(&mut self, effect: F) {
+    pub fn use_effect_always<C: FnOnce() + 'static, F: Fn(&EffectHandle) -> Option<C> + Clone + 'static>(&mut self, effect: F) {
+        self.track_hook::<EffectStore<Option<()>, F, C>>("use_effect_always");
         if self.has_init {
             let hook_ref = self.effect_hooks.get();
             let original_effect = hook_ref.clone().downcast_rc::<EffectStore<Option<()>, F, C>>().ok().unwrap();
@@ -250,22 +595,735 @@ impl ComponentScope {
                 effect,
                 cleanup: Rc::new(RefCell::new(None)),
                 basis: None,
-                pending_execution: Cell::new(true)
+                pending_execution: Cell::new(true),
+                handle: EffectHandle::new(&self.renderer)
             }));
         }
     }
 
-    fn create_context_handle<T>(&self) -> ContextConsumerHandle<T> {
-        let context_link = &self.context_link;
-        loop {
-            let cl = context_link.as_ref().unwrap();
-            if let Some(casted) = cl.clone().downcast_rc::<ContextNode<T>>().ok() {
-                casted.renderers.try_borrow_mut().unwrap().push(self.renderer.clone());
-                return ContextConsumerHandle {
-                    context_node: casted
+    pub fn use_layout_effect_always<C: FnOnce() + 'static, F: Fn(&EffectHandle) -> Option<C> + Clone + 'static>(&mut self, effect: F) {
+        self.track_hook::<EffectStore<Option<()>, F, C>>("use_layout_effect_always");
+        if self.has_init {
+            let hook_ref = self.layout_effect_hooks.get();
+            let original_effect = hook_ref.clone().downcast_rc::<EffectStore<Option<()>, F, C>>().ok().unwrap();
+            *hook_ref = Rc::new(original_effect.update(effect, None));
+        } else {
+            self.layout_effect_hooks.hooks.push(Rc::new(EffectStore::<Option<()>, F, C> {
+                effect,
+                cleanup: Rc::new(RefCell::new(None)),
+                basis: None,
+                pending_execution: Cell::new(true),
+                handle: EffectHandle::new(&self.renderer)
+            }));
+        }
+    }
+
+    // Subscribes once the component is committed and again whenever `basis`
+    // changes, e.g. when the props point at another store. The component
+    // re-renders only when the store reports a change to the snapshot.
+    pub fn use_sync_external_store<T: Clone + PartialEq + 'static, U: FnOnce() + 'static, S: Fn(Box<dyn Fn()>) -> U + 'static, G: Fn() -> T + 'static, Basis: Eq + 'static>(&mut self, subscribe: S, get_snapshot: G, basis: Basis) -> T {
+        let get_snapshot: Rc<dyn Fn() -> T> = Rc::new(get_snapshot);
+        let snapshot = get_snapshot();
+        let rendered = self.use_ref::<RenderedSnapshot<T>>();
+        rendered.replace(Some((get_snapshot, snapshot.clone())));
+        let subscribed = rendered.clone();
+        self.use_layout_effect(move |handle| {
+            let rendered = subscribed.clone();
+            let handle = handle.clone();
+            Some(subscribe(Box::new(move || {
+                if snapshot_changed(&rendered) {
+                    handle.request_update();
                 }
+            })))
+        }, basis);
+        // The store may have changed between render and commit, or before the
+        // subscription above was in place; render again so the committed tree
+        // never shows a torn snapshot.
+        self.use_layout_effect_always(move |handle| {
+            if snapshot_changed(&rendered) {
+                handle.request_update();
             }
+            None::<fn() -> ()>
+        });
+        snapshot
+    }
+
+    fn create_context_handle<T: 'static>(&self, context: &'static Context<T>) -> ContextConsumerHandle<T> {
+        let context_node = find_context_node(&self.context_link, context);
+        if let Some(node) = context_node.as_ref() {
+            node.subscribe(self.renderer.clone(), None);
+        }
+        ContextConsumerHandle {
+            default_value: Rc::new(context.default_value()),
+            context_node
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::future::{Future, poll_fn};
+    use std::task::{Poll, Waker};
+    use crate::scope::{CallbackHandle, ComponentScope, Context, NilRef, Updater, FutureStatus, LocalExecutor, ManualClock, RefObject, StateHandle, StateSetter, flush};
+    use crate::v_node::{ComponentModel, VComponentElement, VNode, ct};
+    use crate::renderer::test_native::{Log, mount_root, update_root, unmount_root};
+
+    thread_local! {
+        static RESULTS: RefCell<HashMap<u32, Result<u32, String>>> = RefCell::new(HashMap::new());
+        static WAKERS: RefCell<Vec<Waker>> = const { RefCell::new(vec![]) };
+    }
+
+    fn fetch(id: &u32) -> impl Future<Output = Result<u32, String>> {
+        let id = *id;
+        poll_fn(move |cx| match RESULTS.with(|results| results.borrow().get(&id).cloned()) {
+            Some(result) => Poll::Ready(result),
+            None => {
+                WAKERS.with(|wakers| wakers.borrow_mut().push(cx.waker().clone()));
+                Poll::Pending
+            }
+        })
+    }
+
+    fn resolve(id: u32, result: Result<u32, String>) {
+        RESULTS.with(|results| results.borrow_mut().insert(id, result));
+        for waker in WAKERS.with(|wakers| std::mem::take(&mut *wakers.borrow_mut())) {
+            waker.wake();
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct Fetches {
+        id: u32,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Fetches {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            self.log.push(match scope.use_future(fetch, self.id) {
+                FutureStatus::Pending => format!("{} pending", self.id),
+                FutureStatus::Ready(value) => format!("{} ready {}", self.id, value),
+                FutureStatus::Error(error) => format!("{} error {}", self.id, error)
+            });
+            VNode::Native(String::from("fetches"))
+        }
+    }
+
+    #[test]
+    fn use_future_is_pending_again_when_the_basis_changes() {
+        let executor = Rc::new(LocalExecutor::new());
+        let mut updater = Updater::new();
+        updater.set_executor(executor.clone());
+        let updater = Rc::new(RefCell::new(updater));
+        let log = Log::new();
+        let root = mount_root(Fetches { id: 1, log: log.clone() }, &updater);
+        executor.run_until_stalled();
+        assert_eq!(log.take(), vec!["1 pending"]);
+
+        resolve(1, Ok(10));
+        executor.run_until_stalled();
+        assert_eq!(log.take(), vec!["1 ready 10"]);
+
+        update_root(&root, Fetches { id: 2, log: log.clone() }, &updater);
+        executor.run_until_stalled();
+        assert_eq!(log.take(), vec!["2 pending"]);
+
+        resolve(2, Err(String::from("offline")));
+        executor.run_until_stalled();
+        assert_eq!(log.take(), vec!["2 error offline"]);
+    }
+
+    #[test]
+    fn use_future_cancels_the_task_on_unmount() {
+        let executor = Rc::new(LocalExecutor::new());
+        let mut updater = Updater::new();
+        updater.set_executor(executor.clone());
+        let updater = Rc::new(RefCell::new(updater));
+        let log = Log::new();
+        let root = mount_root(Fetches { id: 3, log: log.clone() }, &updater);
+        executor.run_until_stalled();
+        assert_eq!(executor.pending_count(), 1);
+
+        unmount_root(&root, &updater);
+        executor.run_until_stalled();
+        assert_eq!(executor.pending_count(), 0);
+        resolve(3, Ok(30));
+        executor.run_until_stalled();
+        assert_eq!(log.take(), vec!["3 pending"]);
+    }
+
+    #[test]
+    fn use_future_mounts_on_roots_without_an_executor() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let log = Log::new();
+        let root = mount_root(Fetches { id: 4, log: log.clone() }, &updater);
+        unmount_root(&root, &updater);
+        assert_eq!(log.take(), vec!["4 pending"]);
+    }
+
+    type Listeners = Rc<RefCell<Vec<Rc<dyn Fn()>>>>;
+
+    #[derive(Clone)]
+    struct Counter {
+        value: Rc<RefCell<u32>>,
+        listeners: Listeners
+    }
+
+    impl PartialEq for Counter {
+        fn eq(&self, other: &Counter) -> bool {
+            Rc::ptr_eq(&self.value, &other.value)
+        }
+    }
+
+    impl Eq for Counter {
+    }
+
+    impl Counter {
+        fn new(value: u32) -> Counter {
+            Counter {
+                value: Rc::new(RefCell::new(value)),
+                listeners: Rc::new(RefCell::new(vec![]))
+            }
+        }
+
+        fn listener_count(&self) -> usize {
+            self.listeners.borrow().len()
+        }
+
+        fn set(&self, value: u32) {
+            *self.value.borrow_mut() = value;
+            self.notify();
+        }
+
+        fn increment(&self) {
+            *self.value.borrow_mut() += 1;
+            self.notify();
+        }
+
+        fn notify(&self) {
+            let listeners = self.listeners.borrow().clone();
+            for listener in listeners.iter() {
+                listener();
+            }
+        }
+    }
+
+    // Changes the store from a layout effect without telling its listeners,
+    // like a write that lands between render and commit.
+    #[derive(PartialEq)]
+    struct WritesBehindTheStore {
+        counter: Counter
+    }
+
+    impl ComponentModel<String, ()> for WritesBehindTheStore {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let value = self.counter.value.clone();
+            scope.use_layout_effect(move |_| {
+                *value.borrow_mut() += 1;
+                None::<fn() -> ()>
+            }, ());
+            VNode::Native(String::from("writes behind the store"))
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct ShowsCounter {
+        counter: Counter,
+        tears: bool,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for ShowsCounter {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let listeners = self.counter.listeners.clone();
+            let value = self.counter.value.clone();
+            let count = scope.use_sync_external_store(move |callback| {
+                let callback: Rc<dyn Fn()> = Rc::from(callback);
+                listeners.borrow_mut().push(callback.clone());
+                let listeners = listeners.clone();
+                move || listeners.borrow_mut().retain(|listener| !Rc::ptr_eq(listener, &callback))
+            }, move || *value.borrow(), self.counter.clone());
+            self.log.push(format!("count {}", count));
+            if self.tears {
+                VNode::component(VComponentElement::new(WritesBehindTheStore { counter: self.counter.clone() }, None))
+            } else {
+                VNode::Native(String::from("counter"))
+            }
+        }
+    }
+
+    #[test]
+    fn use_sync_external_store_follows_the_store_until_unmounted() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let counter = Counter::new(0);
+        let log = Log::new();
+        let root = mount_root(ShowsCounter { counter: counter.clone(), tears: false, log: log.clone() }, &updater);
+        assert_eq!(log.take(), vec!["count 0"]);
+        assert_eq!(counter.listener_count(), 1);
+
+        counter.increment();
+        assert_eq!(log.take(), vec!["count 1"]);
+        counter.set(1);
+        assert!(log.take().is_empty());
+
+        unmount_root(&root, &updater);
+        assert_eq!(counter.listener_count(), 0);
+        counter.increment();
+        assert!(log.take().is_empty());
+    }
+
+    #[test]
+    fn use_sync_external_store_moves_its_subscription_to_a_new_store() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let (a, b) = (Counter::new(1), Counter::new(100));
+        let log = Log::new();
+        let root = mount_root(ShowsCounter { counter: a.clone(), tears: false, log: log.clone() }, &updater);
+        update_root(&root, ShowsCounter { counter: b.clone(), tears: false, log: log.clone() }, &updater);
+        assert_eq!(log.take(), vec!["count 1", "count 100"]);
+        assert_eq!((a.listener_count(), b.listener_count()), (0, 1));
+
+        b.set(200);
+        assert_eq!(log.take(), vec!["count 200"]);
+        a.set(2);
+        assert!(log.take().is_empty());
+    }
+
+    #[test]
+    fn use_sync_external_store_renders_again_when_the_store_changed_before_commit() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let counter = Counter::new(0);
+        let log = Log::new();
+        let _root = mount_root(ShowsCounter { counter: counter.clone(), tears: true, log: log.clone() }, &updater);
+        assert_eq!(log.take(), vec!["count 0", "count 1"]);
+    }
+
+    #[derive(PartialEq)]
+    struct Debounces {
+        query: &'static str,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Debounces {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let debounced = scope.use_debounce(self.query, 100);
+            self.log.push(format!("{} / {}", self.query, debounced));
+            VNode::Native(String::from("debounces"))
+        }
+    }
+
+    #[test]
+    fn use_debounce_settles_once_the_value_stops_changing() {
+        let clock = Rc::new(ManualClock::new());
+        let mut updater = Updater::new();
+        updater.set_clock(clock.clone());
+        let updater = Rc::new(RefCell::new(updater));
+        let log = Log::new();
+        let root = mount_root(Debounces { query: "a", log: log.clone() }, &updater);
+        clock.advance(50);
+        update_root(&root, Debounces { query: "ab", log: log.clone() }, &updater);
+        clock.advance(99);
+        assert_eq!(log.take(), vec!["a / a", "ab / a"]);
+
+        clock.advance(1);
+        assert_eq!(log.take(), vec!["ab / ab"]);
+        assert_eq!(clock.pending_count(), 0);
+    }
+
+    #[derive(PartialEq)]
+    struct Ticks {
+        period_ms: Option<u32>,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Ticks {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let (ticks, ticks_handle) = scope.use_state(0);
+            scope.use_interval(move |handle| handle.update_state_map(ticks_handle, |ticks| ticks + 1), self.period_ms);
+            self.log.push(format!("ticks {}", ticks));
+            VNode::Native(String::from("ticks"))
+        }
+    }
+
+    #[test]
+    fn use_interval_stops_when_the_period_is_removed() {
+        let clock = Rc::new(ManualClock::new());
+        let mut updater = Updater::new();
+        updater.set_clock(clock.clone());
+        let updater = Rc::new(RefCell::new(updater));
+        let log = Log::new();
+        let root = mount_root(Ticks { period_ms: Some(10), log: log.clone() }, &updater);
+        clock.advance(25);
+        assert_eq!(log.take(), vec!["ticks 0", "ticks 1", "ticks 2"]);
+
+        update_root(&root, Ticks { period_ms: None, log: log.clone() }, &updater);
+        clock.advance(100);
+        assert_eq!(log.take(), vec!["ticks 2"]);
+        assert_eq!(clock.pending_count(), 0);
+    }
+
+    #[test]
+    fn timer_hooks_mount_on_roots_without_a_clock() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let log = Log::new();
+        let root = mount_root(Ticks { period_ms: Some(10), log: log.clone() }, &updater);
+        update_root(&root, Ticks { period_ms: Some(20), log: log.clone() }, &updater);
+        unmount_root(&root, &updater);
+        assert_eq!(log.take(), vec!["ticks 0", "ticks 0"]);
+    }
+
+    #[derive(PartialEq)]
+    struct ExportsSetter {
+        slot: RefObject<StateSetter<u32>>,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for ExportsSetter {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let (value, setter) = scope.use_state_setter(0);
+            self.slot.replace(Some(setter));
+            self.log.push(format!("exports {}", value));
+            VNode::Native(String::from("exports setter"))
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct Replacement {
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Replacement {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let (value, _) = scope.use_state(0u32);
+            self.log.push(format!("replacement {}", value));
+            VNode::Native(String::from("replacement"))
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct Swaps {
+        replaced: bool,
+        slot: RefObject<StateSetter<u32>>,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Swaps {
+        fn render(&self, _: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            if self.replaced {
+                VNode::component(VComponentElement::new(Replacement { log: self.log.clone() }, None))
+            } else {
+                VNode::component(VComponentElement::new(ExportsSetter { slot: self.slot.clone(), log: self.log.clone() }, None))
+            }
+        }
+    }
+
+    #[test]
+    fn setters_of_a_replaced_component_are_ignored() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let slot = RefObject::new();
+        let log = Log::new();
+        let root = mount_root(Swaps { replaced: false, slot: slot.clone(), log: log.clone() }, &updater);
+        let setter = slot.borrow_mut().take().unwrap();
+        setter.set(1);
+        assert_eq!(log.take(), vec!["exports 0", "exports 1"]);
+
+        update_root(&root, Swaps { replaced: true, slot: slot.clone(), log: log.clone() }, &updater);
+        setter.set(2);
+        flush(&updater);
+        assert_eq!(log.take(), vec!["replacement 0"]);
+    }
+
+    #[derive(PartialEq)]
+    struct BorrowsHandle {
+        slot: RefObject<StateHandle<u32>>
+    }
+
+    impl ComponentModel<String, ()> for BorrowsHandle {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let handle = self.slot.borrow_mut().unwrap();
+            scope.use_setter(handle);
+            VNode::Native(String::from("borrows handle"))
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct LendsHandle;
+
+    impl ComponentModel<String, ()> for LendsHandle {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let (_, handle) = scope.use_state(0u32);
+            let slot = RefObject::new();
+            slot.replace(Some(handle));
+            VNode::component(VComponentElement::new(BorrowsHandle { slot }, None))
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "StateHandle created by component")]
+    fn state_handles_still_panic_in_another_component() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        mount_root(LendsHandle, &updater);
+    }
+
+    #[derive(PartialEq)]
+    struct Labelled {
+        round: u32,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Labelled {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let input_id = scope.use_id();
+            let hint_id = scope.use_id();
+            self.log.push(format!("{} {}", input_id, hint_id));
+            VNode::Native(String::from("labelled"))
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct LabelledList {
+        keys: Vec<&'static str>,
+        round: u32,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for LabelledList {
+        fn render(&self, _: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            VNode::Fragment(self.keys.iter().map(|key| {
+                (String::from(*key), VNode::component(VComponentElement::new(Labelled { round: self.round, log: self.log.clone() }, None)))
+            }).collect())
+        }
+    }
+
+    #[test]
+    fn use_id_is_stable_across_renders_and_safe_for_id_attributes() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let log = Log::new();
+        let root = mount_root(LabelledList { keys: vec!["first task", "a-b", "a_2d_b"], round: 0, log: log.clone() }, &updater);
+        let ids = log.take();
+        update_root(&root, LabelledList { keys: vec!["first task", "a-b", "a_2d_b"], round: 1, log: log.clone() }, &updater);
+        assert_eq!(log.take(), ids);
+
+        let ids: Vec<&str> = ids.iter().flat_map(|pair| pair.split(' ')).collect();
+        assert!(ids.iter().all(|id| id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')));
+        let mut unique = ids.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 6);
+    }
+
+    static SETTINGS: Context<(u32, u32)> = Context::new(|| (0, 0));
+
+    #[derive(PartialEq)]
+    struct SelectsSetting {
+        second: bool,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for SelectsSetting {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let (name, value) = if self.second {
+                ("second", scope.use_context_selector(&SETTINGS, |settings| settings.1))
+            } else {
+                ("first", scope.use_context_selector(&SETTINGS, |settings| settings.0))
+            };
+            self.log.push(format!("{} {}", name, value));
+            VNode::Native(String::from(name))
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct ProvidesSettings {
+        settings: (u32, u32),
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for ProvidesSettings {
+        fn render(&self, _: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let consumer = |second| VNode::component(VComponentElement::new(SelectsSetting { second, log: self.log.clone() }, None));
+            ct(&SETTINGS, self.settings, VNode::Fragment(vec![(String::from("first"), consumer(false)), (String::from("second"), consumer(true))]))
+        }
+    }
+
+    #[test]
+    fn use_context_selector_skips_consumers_whose_selection_is_unchanged() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let log = Log::new();
+        let root = mount_root(ProvidesSettings { settings: (0, 0), log: log.clone() }, &updater);
+        assert_eq!(log.take(), vec!["first 0", "second 0"]);
+
+        update_root(&root, ProvidesSettings { settings: (1, 0), log: log.clone() }, &updater);
+        assert_eq!(log.take(), vec!["first 1"]);
+
+        update_root(&root, ProvidesSettings { settings: (1, 5), log: log.clone() }, &updater);
+        assert_eq!(log.take(), vec!["second 5"]);
+    }
+
+    enum Tally {
+        Add(u32),
+        Reset
+    }
+
+    #[derive(PartialEq)]
+    struct Tallies {
+        slot: RefObject<CallbackHandle<Tally>>,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Tallies {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let (total, dispatch) = scope.use_reducer(|total: &u32, action| match action {
+                Tally::Add(amount) => total + amount,
+                Tally::Reset => 0
+            }, 0);
+            self.slot.replace(Some(scope.use_callback(move |scope, action| dispatch.dispatch(scope, action))));
+            self.log.push(format!("total {}", total));
+            VNode::Native(String::from("tallies"))
+        }
+    }
+
+    #[test]
+    fn use_reducer_skips_the_render_when_the_state_is_unchanged() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let (slot, log) = (RefObject::new(), Log::new());
+        let _root = mount_root(Tallies { slot: slot.clone(), log: log.clone() }, &updater);
+        let dispatch = slot.borrow_mut().take().unwrap();
+        assert_eq!(log.take(), vec!["total 0"]);
+
+        dispatch.trigger(Tally::Add(0));
+        dispatch.trigger(Tally::Reset);
+        assert!(log.take().is_empty());
+
+        dispatch.trigger(Tally::Add(2));
+        assert_eq!(log.take(), vec!["total 2"]);
+        dispatch.trigger(Tally::Reset);
+        assert_eq!(log.take(), vec!["total 0"]);
+    }
+
+    struct FieldHandle {
+        label: &'static str
+    }
+
+    #[derive(PartialEq)]
+    struct Field {
+        label: &'static str
+    }
+
+    impl ComponentModel<String, FieldHandle> for Field {
+        fn render(&self, scope: &mut ComponentScope, self_ref: &Option<RefObject<FieldHandle>>) -> VNode<String> {
+            let label = self.label;
+            scope.use_imperative_handle(self_ref, move || FieldHandle { label }, label);
+            VNode::Native(String::from(label))
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct HoldsField {
+        label: Option<&'static str>,
+        field: RefObject<FieldHandle>
+    }
+
+    impl ComponentModel<String, ()> for HoldsField {
+        fn render(&self, _: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            match self.label {
+                Some(label) => VNode::component(VComponentElement::new(Field { label }, Some(self.field.clone()))),
+                None => VNode::Native(String::from("no field"))
+            }
+        }
+    }
+
+    #[test]
+    fn use_imperative_handle_fills_the_parent_ref_until_unmounted() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let field = RefObject::new();
+        let label = |field: &RefObject<FieldHandle>| field.borrow_mut().as_ref().map(|handle| handle.label);
+        let root = mount_root(HoldsField { label: Some("name"), field: field.clone() }, &updater);
+        assert_eq!(label(&field), Some("name"));
+
+        update_root(&root, HoldsField { label: Some("email"), field: field.clone() }, &updater);
+        assert_eq!(label(&field), Some("email"));
+
+        update_root(&root, HoldsField { label: None, field: field.clone() }, &updater);
+        assert_eq!(label(&field), None);
+    }
+
+    #[derive(PartialEq)]
+    struct Collects {
+        slot: RefObject<CallbackHandle<(u32, bool)>>,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Collects {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let (items, items_handle) = scope.use_mut_state(Vec::new);
+            self.slot.replace(Some(scope.use_callback(move |scope, (item, notify)| {
+                items_handle.mutate(scope, |items| items.push(item));
+                if notify {
+                    scope.mark_update();
+                }
+            })));
+            self.log.push(format!("items {:?}", items.try_borrow().unwrap()));
+            VNode::Native(String::from("collects"))
+        }
+    }
+
+    #[test]
+    fn use_mut_state_renders_in_place_changes_once_marked() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let (slot, log) = (RefObject::new(), Log::new());
+        let _root = mount_root(Collects { slot: slot.clone(), log: log.clone() }, &updater);
+        let push = slot.borrow_mut().take().unwrap();
+        assert_eq!(log.take(), vec!["items []"]);
+
+        push.trigger((1, false));
+        assert!(log.take().is_empty());
+
+        push.trigger((2, true));
+        assert_eq!(log.take(), vec!["items [1, 2]"]);
+    }
+
+    static THEME: Context<&'static str> = Context::new(|| "light");
+    static ACCENT: Context<&'static str> = Context::new(|| "grey");
+
+    #[derive(PartialEq)]
+    struct ReadsTheme {
+        name: &'static str,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for ReadsTheme {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let theme = scope.use_context(&THEME);
+            self.log.push(format!("{} {}", self.name, theme));
+            VNode::Native(String::from(self.name))
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct Themed {
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Themed {
+        fn render(&self, _: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let reader = |name| (String::from(name), VNode::component(VComponentElement::new(ReadsTheme { name, log: self.log.clone() }, None)));
+            VNode::Fragment(vec![
+                reader("unprovided"),
+                (String::from("dark"), ct(&THEME, "dark", VNode::Fragment(vec![
+                    reader("outer"),
+                    (String::from("blue"), ct(&THEME, "blue", VNode::Fragment(vec![reader("inner")]))),
+                    (String::from("accent"), ct(&ACCENT, "red", VNode::Fragment(vec![reader("accented")])))
+                ])))
+            ])
+        }
+    }
+
+    #[test]
+    fn use_context_reads_the_nearest_provider_of_its_token_or_the_default() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let log = Log::new();
+        let _root = mount_root(Themed { log: log.clone() }, &updater);
+        assert_eq!(log.take(), vec!["unprovided light", "outer dark", "inner blue", "accented dark"]);
+    }
+}
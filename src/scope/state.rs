@@ -1,15 +1,33 @@
-use std::rc::Rc;
+use std::rc::Weak;
+use std::cell::RefCell;
 use downcast_rs::Downcast;
 use crate::scope::scope::Scope;
+use crate::scope::renderer::Renderer;
+use crate::scope::updater::update_if_mounted;
+
+// Identifies the scope a state handle was created by, so a handle leaked to
+// another component cannot silently read or write that component's hooks.
+#[derive(Clone, Copy)]
+pub struct HookOwner {
+    pub scope_id: usize,
+    pub component_name: &'static str
+}
+
+impl PartialEq for HookOwner {
+    fn eq(&self, other: &Self) -> bool {
+        self.scope_id == other.scope_id
+    }
+}
 
 pub struct StateHandle<T: Clone + PartialEq + 'static> {
+    owner: HookOwner,
     index: usize,
     phantom: std::marker::PhantomData<T>
 }
 
 impl<T: Clone + PartialEq + 'static> PartialEq for StateHandle<T> {
     fn eq(&self, other: &Self) -> bool {
-       self.index == other.index
+       self.owner == other.owner && self.index == other.index
     }
 }
 
@@ -24,25 +42,79 @@ impl<T: Clone + PartialEq + 'static> Clone for StateHandle<T> {
 }
 
 impl<T: Clone + PartialEq + 'static> StateHandle<T> {
+    pub fn owner(&self) -> HookOwner {
+        self.owner
+    }
+
     pub fn update_map<F: FnOnce(&T) -> T>(&self, scope: &mut Scope, mapper: F) {
+        scope.assert_owner(self.owner, "StateHandle");
         scope.update_state_map(self.index, mapper)
     }
 
     pub fn update(&self, scope: &mut Scope, new_value: T) {
+        scope.assert_owner(self.owner, "StateHandle");
         scope.update_state(self.index, new_value)
     }
 }
 
+pub struct StateSetter<T: Clone + PartialEq + 'static> {
+    pub handle: StateHandle<T>,
+    pub renderer: Weak<RefCell<dyn Renderer>>,
+}
+
+impl<T: Clone + PartialEq + 'static> Clone for StateSetter<T> {
+    fn clone(&self) -> StateSetter<T> {
+        StateSetter {
+            handle: self.handle,
+            renderer: self.renderer.clone()
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + 'static> PartialEq for StateSetter<T> {
+    fn eq(&self, other: &StateSetter<T>) -> bool {
+        self.handle == other.handle && self.renderer.ptr_eq(&other.renderer)
+    }
+}
+
+impl<T: Clone + PartialEq + 'static> StateSetter<T> {
+    pub fn set(&self, new_value: T) {
+        let handle = self.handle;
+        self.schedule(move |scope| {
+            handle.update(scope, new_value)
+        });
+    }
+
+    pub fn set_map<F: FnOnce(&T) -> T + 'static>(&self, mapper: F) {
+        let handle = self.handle;
+        self.schedule(move |scope| {
+            handle.update_map(scope, mapper)
+        });
+    }
+
+    fn schedule<F: FnOnce(&mut Scope) + 'static>(&self, update_func: F) {
+        let owner = self.handle.owner;
+        update_if_mounted(&self.renderer, move |scope| {
+            // The mount may now hold a different component whose state this
+            // setter knows nothing about; the old state is gone, so drop it.
+            if scope.owns(owner) {
+                update_func(scope);
+            }
+        });
+    }
+}
+
 pub struct StateStore<T: Clone + PartialEq + 'static> {
     pub value: T,
     pub handle: StateHandle<T>
 }
 
 impl<T: Clone + PartialEq + 'static> StateStore<T> {
-    pub fn new(value: T, index: usize) -> StateStore<T> {
+    pub fn new(value: T, owner: HookOwner, index: usize) -> StateStore<T> {
         StateStore {
             value,
             handle: StateHandle {
+                owner,
                 index,
                 phantom: std::marker::PhantomData
             }
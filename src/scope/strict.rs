@@ -0,0 +1,89 @@
+use std::cell::RefCell;
+use crate::scope::context::{Context, ContextLink, find_context_node};
+
+pub static STRICT_MODE: Context<bool> = Context::new(|| false);
+
+pub const STRICT_RENDER_LIMIT: usize = 25;
+
+thread_local! {
+    static RENDERING: RefCell<Vec<(&'static str, bool)>> = const { RefCell::new(vec![]) };
+}
+
+pub fn is_strict(context_link: &ContextLink) -> bool {
+    cfg!(debug_assertions) && find_context_node(context_link, &STRICT_MODE).map(|node| {
+        *node.value.try_borrow().unwrap().as_ref()
+    }).unwrap_or(false)
+}
+
+pub fn start_render(component_name: &'static str, strict: bool) {
+    RENDERING.with(|r| r.try_borrow_mut().unwrap().push((component_name, strict)));
+}
+
+pub fn end_render() {
+    RENDERING.with(|r| r.try_borrow_mut().unwrap().pop());
+}
+
+pub fn check_update_during_render() {
+    let rendering = RENDERING.with(|r| r.try_borrow().unwrap().last().cloned());
+    if let Some((component_name, true)) = rendering {
+        warn(&format!("state update scheduled while rendering component `{}`; render functions should be pure, move the update into a callback or an effect", component_name));
+    }
+}
+
+pub fn warn(message: &str) {
+    log_warning(&format!("[strict mode] {}", message));
+}
+
+pub fn log_warning(message: &str) {
+    #[cfg(target_arch = "wasm32")]
+    web_sys::console::warn_1(&wasm_bindgen::JsValue::from(message));
+    #[cfg(not(target_arch = "wasm32"))]
+    eprintln!("{}", message);
+}
+
+// Strict mode only double renders in debug builds.
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    use crate::scope::{ComponentScope, NilRef, Updater};
+    use crate::v_node::{ComponentModel, VComponentElement, VNode, strict};
+    use crate::renderer::test_native::{Log, mount_root};
+
+    #[derive(PartialEq)]
+    struct Renders {
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Renders {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let log = self.log.clone();
+            log.push("render");
+            scope.use_effect(move |_| {
+                log.push("effect");
+                let log = log.clone();
+                Some(move || log.push("cleanup"))
+            }, ());
+            VNode::Native(String::from("renders"))
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct Strict {
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Strict {
+        fn render(&self, _: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            strict(VNode::component(VComponentElement::new(Renders { log: self.log.clone() }, None)))
+        }
+    }
+
+    #[test]
+    fn strict_mode_renders_twice_and_remounts_effects() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let log = Log::new();
+        let _root = mount_root(Strict { log: log.clone() }, &updater);
+        assert_eq!(log.take(), vec!["render", "render", "effect", "cleanup", "effect"]);
+    }
+}
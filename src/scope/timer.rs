@@ -0,0 +1,165 @@
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+pub type TimerId = usize;
+
+pub trait Clock {
+    fn set_timeout(&self, delay_ms: u32, callback: Box<dyn FnOnce()>) -> TimerId;
+    fn set_interval(&self, period_ms: u32, callback: Box<dyn Fn()>) -> TimerId;
+    fn clear(&self, id: TimerId);
+}
+
+enum TimerCallback {
+    Once(Box<dyn FnOnce()>),
+    Repeat(Rc<dyn Fn()>)
+}
+
+struct ManualTimer {
+    id: TimerId,
+    due: u64,
+    period: Option<u64>,
+    callback: TimerCallback
+}
+
+// Clock whose time only moves when `advance` is called, firing due timers in
+// order, so timer hooks can be driven deterministically outside a browser.
+pub struct ManualClock {
+    now: Cell<u64>,
+    next_id: Cell<TimerId>,
+    timers: RefCell<Vec<ManualTimer>>
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock {
+            now: Cell::new(0),
+            next_id: Cell::new(1),
+            timers: RefCell::new(vec![])
+        }
+    }
+
+    pub fn now(&self) -> u64 {
+        self.now.get()
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.timers.try_borrow().unwrap().len()
+    }
+
+    pub fn advance(&self, ms: u64) {
+        let target = self.now.get() + ms;
+        loop {
+            let timer = {
+                let mut timers = self.timers.try_borrow_mut().unwrap();
+                let next = timers.iter().enumerate().filter(|(_, t)| {
+                    t.due <= target
+                }).min_by_key(|(_, t)| {
+                    (t.due, t.id)
+                }).map(|(index, _)| index);
+                match next {
+                    Some(index) => timers.remove(index),
+                    None => break
+                }
+            };
+            self.now.set(timer.due);
+            match timer.callback {
+                TimerCallback::Once(callback) => callback(),
+                TimerCallback::Repeat(callback) => {
+                    let period = timer.period.unwrap();
+                    self.timers.try_borrow_mut().unwrap().push(ManualTimer {
+                        id: timer.id,
+                        due: timer.due + period,
+                        period: timer.period,
+                        callback: TimerCallback::Repeat(callback.clone())
+                    });
+                    callback();
+                }
+            }
+        }
+        self.now.set(target);
+    }
+
+    fn add_timer(&self, delay_ms: u32, period: Option<u64>, callback: TimerCallback) -> TimerId {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.timers.try_borrow_mut().unwrap().push(ManualTimer {
+            id,
+            due: self.now.get() + delay_ms as u64,
+            period,
+            callback
+        });
+        id
+    }
+}
+
+impl Clock for ManualClock {
+    fn set_timeout(&self, delay_ms: u32, callback: Box<dyn FnOnce()>) -> TimerId {
+        self.add_timer(delay_ms, None, TimerCallback::Once(callback))
+    }
+
+    fn set_interval(&self, period_ms: u32, callback: Box<dyn Fn()>) -> TimerId {
+        // A zero period would fire forever within a single `advance`.
+        let period = std::cmp::max(period_ms, 1) as u64;
+        self.add_timer(period_ms, Some(period), TimerCallback::Repeat(Rc::from(callback)))
+    }
+
+    fn clear(&self, id: TimerId) {
+        self.timers.try_borrow_mut().unwrap().retain(|t| t.id != id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Fired = Rc<RefCell<Vec<(&'static str, u64)>>>;
+
+    fn recorder() -> (Fired, Rc<ManualClock>) {
+        (Rc::new(RefCell::new(vec![])), Rc::new(ManualClock::new()))
+    }
+
+    #[test]
+    fn timeouts_fire_in_due_order_with_the_clock_at_their_due_time() {
+        let (fired, clock) = recorder();
+        for (name, delay) in [("late", 30), ("early", 10), ("tie", 10)] {
+            let fired = fired.clone();
+            let at = clock.clone();
+            clock.set_timeout(delay, Box::new(move || fired.borrow_mut().push((name, at.now()))));
+        }
+        clock.advance(20);
+        assert_eq!(*fired.borrow(), vec![("early", 10), ("tie", 10)]);
+        assert_eq!(clock.now(), 20);
+        clock.advance(10);
+        assert_eq!(*fired.borrow(), vec![("early", 10), ("tie", 10), ("late", 30)]);
+        assert_eq!(clock.pending_count(), 0);
+    }
+
+    #[test]
+    fn intervals_repeat_until_cleared() {
+        let (fired, clock) = recorder();
+        let at = clock.clone();
+        let recorded = fired.clone();
+        let id = clock.set_interval(10, Box::new(move || recorded.borrow_mut().push(("tick", at.now()))));
+        clock.advance(35);
+        assert_eq!(*fired.borrow(), vec![("tick", 10), ("tick", 20), ("tick", 30)]);
+        clock.clear(id);
+        clock.advance(100);
+        assert_eq!(fired.borrow().len(), 3);
+        assert_eq!(clock.pending_count(), 0);
+    }
+
+    #[test]
+    fn zero_period_intervals_still_let_time_advance() {
+        let (fired, clock) = recorder();
+        let recorded = fired.clone();
+        clock.set_interval(0, Box::new(move || recorded.borrow_mut().push(("tick", 0))));
+        clock.advance(3);
+        assert_eq!(fired.borrow().len(), 4);
+    }
+}
@@ -2,69 +2,1066 @@ use std::rc::{Rc, Weak};
 use std::cell::RefCell;
 use crate::scope::renderer::Renderer;
 use crate::scope::scope::Scope;
-use crate::scope::effect::EffectStoreT;
+use crate::scope::effect_queue::{EffectQueue, UnmountCleanup, run_effects};
+use crate::scope::future::{Executor, LocalExecutor};
+use crate::scope::timer::{Clock, ManualClock};
+use crate::scope::scheduler::{Scheduler, SyncScheduler, Deadline};
+use crate::scope::lane::{Lane, current_lane, with_lane};
+use crate::scope::strict::{check_update_during_render, log_warning};
+
+pub type DeferredTask = Box<dyn FnOnce()>;
+
+// The lane and tree depth a renderer was marked dirty at.
+type DirtyRenderer = (Lane, usize, Weak<RefCell<dyn Renderer>>);
+
+type DeferredUpdate = (Weak<RefCell<dyn Renderer>>, Lane, Box<dyn FnOnce(&mut Scope)>);
+
+// Number of render rounds a single flush may take before it is treated as an
+// infinite update loop, e.g. an effect that sets state on every run.
+pub const FLUSH_ROUND_LIMIT: usize = 50;
+
+thread_local! {
+    // Updaters whose components are rendering right now, innermost last.
+    static ACTIVE_UPDATERS: RefCell<Vec<Rc<RefCell<Updater>>>> = const { RefCell::new(vec![]) };
+    // Nesting depth of `batch` calls and the updaters they still have to flush.
+    static BATCH: RefCell<(usize, Vec<Rc<RefCell<Updater>>>)> = const { RefCell::new((0, vec![])) };
+}
 
 pub struct Updater {
-    dirty_renderer: Vec<Weak<RefCell<dyn Renderer>>>,
+    dirty_renderer: Vec<DirtyRenderer>,
+    render_depth: usize,
+    deferred_updates: Vec<DeferredUpdate>,
+    slice_limit: Option<usize>,
+    flushing: bool,
+    slice_scheduled: bool,
+    // A slice rendered transition work that is not committed yet.
+    transition_started: bool,
+    passive_effect_scheduler: Option<Rc<dyn Fn(DeferredTask)>>,
+    scheduler: Rc<dyn Scheduler>,
+    committer: Option<Rc<dyn Fn()>>,
+    executor: Rc<dyn Executor>,
+    clock: Rc<dyn Clock>,
+    flush_count: usize,
+    effect_queue: EffectQueue,
+}
+
+impl Default for Updater {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Updater {
     pub fn new() -> Updater {
         Updater {
-            dirty_renderer: vec![]
+            dirty_renderer: vec![],
+            render_depth: 0,
+            deferred_updates: vec![],
+            slice_limit: None,
+            flushing: false,
+            slice_scheduled: false,
+            transition_started: false,
+            passive_effect_scheduler: None,
+            scheduler: Rc::new(SyncScheduler),
+            committer: None,
+            // Futures wait until a platform executor is set, or are driven
+            // by hand through a `LocalExecutor` passed in by native code.
+            executor: Rc::new(LocalExecutor::new()),
+            // Timers wait until a platform clock is set, e.g. by the browser
+            // entry point; native roots can pass a clock they advance.
+            clock: Rc::new(ManualClock::new()),
+            flush_count: 0,
+            effect_queue: EffectQueue::new()
+        }
+    }
+
+    pub fn enqueue_effects(&mut self, scope: &mut Scope, depth: usize) {
+        let remount = scope.take_strict_remount();
+        for e in scope.layout_effects_iter().filter(|e| e.is_pending()) {
+            self.effect_queue.push_layout((e.clone(), remount, depth));
+        }
+        for e in scope.effects_iter().filter(|e| e.is_pending()) {
+            self.effect_queue.push_passive((e.clone(), remount, depth));
+        }
+    }
+
+    pub fn enqueue_unmount_cleanups(&mut self, cleanups: Vec<UnmountCleanup>) {
+        self.effect_queue.push_unmount_cleanups(cleanups);
+    }
+
+    pub fn set_executor(&mut self, executor: Rc<dyn Executor>) {
+        self.executor = executor;
+    }
+
+    pub fn executor(&self) -> Rc<dyn Executor> {
+        self.executor.clone()
+    }
+
+    pub fn flush_count(&self) -> usize {
+        self.flush_count
+    }
+
+    pub fn set_clock(&mut self, clock: Rc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub fn clock(&self) -> Rc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    pub fn set_passive_effect_scheduler<F: Fn(DeferredTask) + 'static>(&mut self, scheduler: F) {
+        self.passive_effect_scheduler = Some(Rc::new(scheduler));
+    }
+
+    // Depth given to components mounted from now on. Components set it to
+    // their own depth plus one while mounting or updating their content.
+    pub fn render_depth(&self) -> usize {
+        self.render_depth
+    }
+
+    pub fn set_render_depth(&mut self, depth: usize) -> usize {
+        std::mem::replace(&mut self.render_depth, depth)
+    }
+
+    // Called between rendering and running effects to apply the native
+    // changes the render produced, usually `NativeMountFactory::commit`.
+    pub fn set_committer<F: Fn() + 'static>(&mut self, committer: F) {
+        self.committer = Some(Rc::new(committer));
+    }
+
+    pub fn set_scheduler(&mut self, scheduler: Rc<dyn Scheduler>) {
+        self.scheduler = scheduler;
+    }
+
+    pub fn scheduler(&self) -> Rc<dyn Scheduler> {
+        self.scheduler.clone()
+    }
+
+    // Renders at most `slice_limit` components per slice and hands the rest
+    // of the flush back to the scheduler. Components updated by their parent
+    // are queued instead of being rendered inside the parent's render.
+    pub fn set_time_slicing(&mut self, slice_limit: usize) {
+        self.slice_limit = Some(std::cmp::max(slice_limit, 1));
+    }
+
+    pub fn is_time_sliced(&self) -> bool {
+        self.slice_limit.is_some()
+    }
+
+    // Returns whether the root now needs a flush it did not need before: on
+    // its first dirty component, and on its first urgent one, since queued
+    // transitions alone only wait for a slice.
+    pub fn mark_update(&mut self, renderer: &Rc<RefCell<dyn Renderer>>, depth: usize, lane: Lane) -> bool {
+        let weak = Rc::downgrade(renderer);
+        let was_idle = self.dirty_renderer.is_empty();
+        let had_urgent = self.dirty_renderer.iter().any(|(l, _, _)| *l == Lane::Urgent);
+        match self.dirty_renderer.iter_mut().find(|(_, _, r)| r.ptr_eq(&weak)) {
+            Some(entry) => entry.0 = std::cmp::min(entry.0, lane),
+            None => self.dirty_renderer.push((lane, depth, weak))
         }
+        was_idle || (lane == Lane::Urgent && !had_urgent)
     }
 
-    pub fn mark_update(&mut self, renderer: &Rc<RefCell<dyn Renderer>>) -> usize {
-        self.dirty_renderer.push(Rc::downgrade(renderer));
-        self.dirty_renderer.len()
+    pub fn has_pending_work(&self) -> bool {
+        !self.dirty_renderer.is_empty() || !self.deferred_updates.is_empty()
     }
 
-    pub fn get_updatable(&mut self) -> Vec<Rc<RefCell<dyn Renderer>>> {
-        let dirty_renderer = std::mem::replace(&mut self.dirty_renderer, vec![]);
-        let mut unwrapped: Vec<Rc<RefCell<dyn Renderer>>> = dirty_renderer.into_iter().filter_map(|r| {
+    // Parents come before their children so that a child re-rendered by its
+    // parent has its update consumed and is skipped when its own turn comes.
+    // Renderers in other lanes stay queued.
+    pub fn get_updatable(&mut self, lane: Lane) -> Vec<Rc<RefCell<dyn Renderer>>> {
+        let (mut updatable, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut self.dirty_renderer).into_iter().partition(|(l, _, _)| *l == lane);
+        self.dirty_renderer = rest;
+        updatable.sort_by_key(|(_, depth, _)| *depth);
+        updatable.into_iter().filter_map(|(_, _, r)| {
             r.upgrade()
-        }).collect();
-        unwrapped.dedup_by(|r1, r2| {
-            std::ptr::eq(r1.as_ref(), r2.as_ref())
-        });
-        unwrapped
+        }).collect()
+    }
+
+    pub fn next_updatable(&mut self) -> Option<(Lane, Rc<RefCell<dyn Renderer>>)> {
+        loop {
+            let index = self.dirty_renderer.iter().enumerate().min_by_key(|(_, (lane, depth, _))| (*lane, *depth)).map(|(index, _)| index)?;
+            let (lane, _, renderer) = self.dirty_renderer.remove(index);
+            if let Some(renderer) = renderer.upgrade() {
+                return Some((lane, renderer));
+            }
+        }
     }
 }
 
-pub fn update<T: FnOnce(&mut Scope)>(renderer: &Rc<RefCell<dyn Renderer>>, update_func: T) {
-    let token = {
-        let mut renderer_mut = renderer.try_borrow_mut().unwrap();
+pub fn enter_render(updater: &Rc<RefCell<Updater>>) {
+    ACTIVE_UPDATERS.with(|u| u.try_borrow_mut().unwrap().push(updater.clone()));
+}
+
+pub fn leave_render() {
+    ACTIVE_UPDATERS.with(|u| u.try_borrow_mut().unwrap().pop());
+}
+
+// A renderer that is borrowed is in the middle of rendering, so the update is
+// parked on the updater driving that render and applied after the pass.
+fn defer_update(renderer: &Rc<RefCell<dyn Renderer>>, update_func: Box<dyn FnOnce(&mut Scope)>) {
+    let updater = ACTIVE_UPDATERS.with(|u| u.try_borrow().unwrap().last().cloned())
+        .expect("update scheduled for a busy component outside of any render");
+    updater.try_borrow_mut().unwrap().deferred_updates.push((Rc::downgrade(renderer), current_lane(), update_func));
+}
+
+pub fn update_if_mounted<T: FnOnce(&mut Scope) + 'static>(renderer: &Weak<RefCell<dyn Renderer>>, update_func: T) {
+    check_update_during_render();
+    if let Some(renderer) = renderer.upgrade() {
+        let is_mounted = match renderer.try_borrow() {
+            Ok(renderer_ref) => renderer_ref.is_mounted(),
+            Err(_) => true
+        };
+        if is_mounted {
+            update(&renderer, update_func);
+        }
+    }
+}
+
+pub fn update<T: FnOnce(&mut Scope) + 'static>(renderer: &Rc<RefCell<dyn Renderer>>, update_func: T) {
+    let (needs_flush, updater) = {
+        let mut renderer_mut = match renderer.try_borrow_mut() {
+            Ok(renderer_mut) => renderer_mut,
+            Err(_) => {
+                defer_update(renderer, Box::new(update_func));
+                return;
+            }
+        };
+        let updater = renderer_mut.updater();
         let result = {
-            let u_mut = renderer_mut.updater();
-            let mut updater = u_mut.try_borrow_mut().unwrap();
-            updater.mark_update(&renderer)
+            let depth = renderer_mut.depth();
+            let mut updater_mut = updater.try_borrow_mut().unwrap();
+            updater_mut.mark_update(renderer, depth, current_lane())
         };
+        // The update function may itself update this component, which is
+        // busy until it returns.
+        enter_render(&updater);
         let scope = renderer_mut.scope_mut();
         update_func(scope);
-        result
+        leave_render();
+        (result, updater)
+    };
+
+    if needs_flush {
+        schedule_flush(updater);
+    }
+}
+
+fn schedule_flush(updater: Rc<RefCell<Updater>>) {
+    let batched = BATCH.with(|b| {
+        let mut batch = b.try_borrow_mut().unwrap();
+        if batch.0 == 0 {
+            return false;
+        }
+        if !batch.1.iter().any(|u| Rc::ptr_eq(u, &updater)) {
+            batch.1.push(updater.clone());
+        }
+        true
+    });
+    if batched {
+        return;
+    }
+    let scheduler = updater.try_borrow().unwrap().scheduler();
+    scheduler.schedule_flush(Box::new(move || {
+        flush(&updater);
+    }));
+}
+
+// Runs `f` and flushes the updates it scheduled once it returns, so a handler
+// that sets several states renders once. Nested batches flush with the
+// outermost one.
+pub fn batch<R, F: FnOnce() -> R>(f: F) -> R {
+    BATCH.with(|b| b.try_borrow_mut().unwrap().0 += 1);
+    let _batch = BatchGuard;
+    f()
+}
+
+// Ends the batch when dropped, so a panic in `f` still restores the depth and
+// the updates made before it are flushed.
+struct BatchGuard;
+
+impl Drop for BatchGuard {
+    fn drop(&mut self) {
+        let updaters = BATCH.with(|b| {
+            let mut batch = b.try_borrow_mut().unwrap();
+            batch.0 -= 1;
+            if batch.0 == 0 {
+                std::mem::take(&mut batch.1)
+            } else {
+                vec![]
+            }
+        });
+        for updater in updaters.into_iter() {
+            schedule_flush(updater);
+        }
+    }
+}
+
+// Updates marked while a flush is in progress, e.g. by effects setting state,
+// are picked up by the next round of the same flush instead of re-entering it.
+// Only urgent updates are rendered here; transitions, and all work of a time
+// sliced root, are left to slices. A transition that a slice already started
+// rendering is finished here though, so the commit never shows half of it.
+pub fn flush(updater: &Rc<RefCell<Updater>>) {
+    {
+        let mut updater_mut = updater.try_borrow_mut().unwrap();
+        if updater_mut.flushing {
+            return;
+        }
+        if updater_mut.is_time_sliced() {
+            drop(updater_mut);
+            ensure_slice_scheduled(updater);
+            return;
+        }
+        updater_mut.flushing = true;
+        updater_mut.flush_count += 1;
+    }
+    enter_render(updater);
+    let mut rounds = 0;
+    loop {
+        apply_deferred_updates(updater);
+        let (updatable, transition) = {
+            let mut updater_mut = updater.try_borrow_mut().unwrap();
+            let transition = if std::mem::replace(&mut updater_mut.transition_started, false) {
+                updater_mut.get_updatable(Lane::Transition)
+            } else {
+                vec![]
+            };
+            (updater_mut.get_updatable(Lane::Urgent), transition)
+        };
+        if updatable.is_empty() && transition.is_empty() && updater.try_borrow().unwrap().effect_queue.is_empty() {
+            break;
+        }
+        rounds += 1;
+        if rounds > FLUSH_ROUND_LIMIT {
+            abort_flush(updater);
+            break;
+        }
+        for r in updatable.into_iter() {
+            r.try_borrow_mut().unwrap().maybe_update();
+        }
+        with_lane(Lane::Transition, || {
+            for r in transition.into_iter() {
+                r.try_borrow_mut().unwrap().maybe_update();
+            }
+        });
+        commit(updater);
+        run_queued_effects(updater);
+    }
+    // Changes made outside a render round, e.g. by the initial mount.
+    commit(updater);
+    leave_render();
+    let has_pending_work = {
+        let mut updater_mut = updater.try_borrow_mut().unwrap();
+        updater_mut.flushing = false;
+        updater_mut.has_pending_work()
+    };
+    if has_pending_work {
+        ensure_slice_scheduled(updater);
+    }
+}
+
+fn ensure_slice_scheduled(updater: &Rc<RefCell<Updater>>) {
+    let scheduler = {
+        let mut updater_mut = updater.try_borrow_mut().unwrap();
+        if updater_mut.slice_scheduled {
+            return;
+        }
+        updater_mut.slice_scheduled = true;
+        updater_mut.scheduler()
     };
-    let updater = {
-        let renderer_ref = renderer.try_borrow().unwrap();
-        renderer_ref.updater().clone()
+    let updater = updater.clone();
+    scheduler.schedule_slice(Box::new(move |deadline| {
+        run_slice(updater, deadline);
+    }));
+}
+
+// One slice of work. Dirty components are rendered most urgent lane first,
+// shallowest first within a lane, until the slice limit or the deadline is
+// hit; the rest is scheduled as a new slice. Effects only run once no
+// component is left to render, so they never observe a half finished update.
+fn run_slice(updater: Rc<RefCell<Updater>>, deadline: &dyn Deadline) {
+    let slice_limit = {
+        let mut updater_mut = updater.try_borrow_mut().unwrap();
+        updater_mut.slice_scheduled = false;
+        if updater_mut.flushing {
+            drop(updater_mut);
+            ensure_slice_scheduled(&updater);
+            return;
+        }
+        updater_mut.flushing = true;
+        updater_mut.flush_count += 1;
+        updater_mut.slice_limit.unwrap_or(usize::MAX)
     };
-    
-    let updatable = {
-        if token == 1 {
-            updater.try_borrow_mut().unwrap().get_updatable()
-        } else {
-            vec![]
+    enter_render(&updater);
+    let mut rendered = 0;
+    let mut rounds = 0;
+    loop {
+        apply_deferred_updates(&updater);
+        if rendered >= slice_limit || (rendered > 0 && deadline.should_yield()) {
+            leave_render();
+            updater.try_borrow_mut().unwrap().flushing = false;
+            ensure_slice_scheduled(&updater);
+            return;
+        }
+        let next = updater.try_borrow_mut().unwrap().next_updatable();
+        match next {
+            Some((lane, r)) => {
+                if lane == Lane::Transition {
+                    updater.try_borrow_mut().unwrap().transition_started = true;
+                }
+                with_lane(lane, || r.try_borrow_mut().unwrap().maybe_update());
+                rendered += 1;
+            },
+            None => {
+                updater.try_borrow_mut().unwrap().transition_started = false;
+                commit(&updater);
+                if updater.try_borrow().unwrap().effect_queue.is_empty() {
+                    break;
+                }
+                rounds += 1;
+                if rounds > FLUSH_ROUND_LIMIT {
+                    abort_flush(&updater);
+                    break;
+                }
+                run_queued_effects(&updater);
+            }
+        }
+    }
+    leave_render();
+    updater.try_borrow_mut().unwrap().flushing = false;
+}
+
+fn apply_deferred_updates(updater: &Rc<RefCell<Updater>>) {
+    let deferred_updates = std::mem::take(&mut updater.try_borrow_mut().unwrap().deferred_updates);
+    for (renderer, lane, update_func) in deferred_updates.into_iter() {
+        if let Some(renderer) = renderer.upgrade() {
+            if renderer.try_borrow().unwrap().is_mounted() {
+                with_lane(lane, || update(&renderer, update_func));
+            }
         }
+    }
+}
+
+fn commit(updater: &Rc<RefCell<Updater>>) {
+    let committer = updater.try_borrow().unwrap().committer.clone();
+    if let Some(committer) = committer {
+        committer();
+    }
+}
+
+fn run_queued_effects(updater: &Rc<RefCell<Updater>>) {
+    let (unmount_cleanups, layout_effects, effects, scheduler) = {
+        let mut updater_mut = updater.try_borrow_mut().unwrap();
+        let queue = &mut updater_mut.effect_queue;
+        (queue.take_unmount_cleanups(), queue.take_layout(), queue.take_passive(), updater_mut.passive_effect_scheduler.clone())
     };
-    let mut effects: Vec<Rc<dyn EffectStoreT>> = vec![];
-    for r in updatable.into_iter() {
-        let mut mut_r = r.try_borrow_mut().unwrap();
-        mut_r.maybe_update();
-        for e in mut_r.scope_mut().effects_iter() {
-            effects.push(e.clone());
+    for cleanup in unmount_cleanups.into_iter() {
+        cleanup();
+    }
+    run_effects(layout_effects);
+    match scheduler {
+        // Deferred effects run outside of any flush, so the updates they make
+        // are batched to re-render once after the whole pass.
+        Some(scheduler) => scheduler(Box::new(move || {
+            batch(|| run_effects(effects));
+        })),
+        None => run_effects(effects)
+    }
+}
+
+fn abort_flush(updater: &Rc<RefCell<Updater>>) {
+    let mut updater_mut = updater.try_borrow_mut().unwrap();
+    updater_mut.dirty_renderer.clear();
+    updater_mut.deferred_updates.clear();
+    updater_mut.transition_started = false;
+    updater_mut.effect_queue.take_layout();
+    updater_mut.effect_queue.take_passive();
+    log_warning(&format!("maximum update depth exceeded: updates were still scheduled after {} render rounds; a component probably sets state unconditionally in an effect or during render", FLUSH_ROUND_LIMIT));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scope::{ComponentScope, NilRef, RefObject, Hook, EffectHandle, StateSetter, Deadline, ManualScheduler, NoDeadline, SliceTask, start_transition};
+    use crate::v_node::{ComponentModel, VComponentElement, VNode};
+    use crate::renderer::test_native::{Log, mount_root, update_root, unmount_root};
+
+    fn deferred_passive_effects(updater: &mut Updater) -> Rc<RefCell<Vec<DeferredTask>>> {
+        let tasks = Rc::new(RefCell::new(vec![]));
+        let queued = tasks.clone();
+        updater.set_passive_effect_scheduler(move |task| queued.try_borrow_mut().unwrap().push(task));
+        tasks
+    }
+
+    fn run_tasks(tasks: &Rc<RefCell<Vec<DeferredTask>>>) {
+        let pending = std::mem::take(&mut *tasks.try_borrow_mut().unwrap());
+        for task in pending.into_iter() {
+            task();
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct SetsTwoStates {
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for SetsTwoStates {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let (a, a_handle) = scope.use_state(0);
+            let (b, b_handle) = scope.use_state(0);
+            self.log.push(format!("render {} {}", a, b));
+            scope.use_effect(move |handle| {
+                handle.update_state(a_handle, 1);
+                handle.update_state(b_handle, 1);
+                None::<fn() -> ()>
+            }, ());
+            VNode::Native(String::from("two states"))
+        }
+    }
+
+    #[test]
+    fn updates_from_one_deferred_effect_pass_render_once() {
+        let mut updater = Updater::new();
+        let tasks = deferred_passive_effects(&mut updater);
+        let updater = Rc::new(RefCell::new(updater));
+        let log = Log::new();
+        let _root = mount_root(SetsTwoStates { log: log.clone() }, &updater);
+        assert_eq!(log.take(), vec!["render 0 0"]);
+        run_tasks(&tasks);
+        assert_eq!(log.take(), vec!["render 1 1"]);
+    }
+
+    #[derive(PartialEq)]
+    struct Nested {
+        name: &'static str,
+        child: Option<&'static str>,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Nested {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let (name, log) = (self.name, self.log.clone());
+            scope.use_layout_effect(move |_| {
+                log.push(format!("layout {}", name));
+                let log = log.clone();
+                Some(move || log.push(format!("layout cleanup {}", name)))
+            }, ());
+            let log = self.log.clone();
+            scope.use_effect(move |_| {
+                log.push(format!("effect {}", name));
+                let log = log.clone();
+                Some(move || log.push(format!("cleanup {}", name)))
+            }, ());
+            match self.child {
+                Some(child) => VNode::component(VComponentElement::new(Nested { name: child, child: None, log: self.log.clone() }, None)),
+                None => VNode::Native(String::from(self.name))
+            }
+        }
+    }
+
+    #[test]
+    fn child_effects_run_before_parent_effects_and_layout_before_passive() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let log = Log::new();
+        let _root = mount_root(Nested { name: "parent", child: Some("child"), log: log.clone() }, &updater);
+        assert_eq!(log.take(), vec!["layout child", "layout parent", "effect child", "effect parent"]);
+    }
+
+    #[test]
+    fn unmount_cleans_up_children_before_parents() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let log = Log::new();
+        let root = mount_root(Nested { name: "parent", child: Some("child"), log: log.clone() }, &updater);
+        log.take();
+        unmount_root(&root, &updater);
+        assert_eq!(log.take(), vec!["layout cleanup child", "cleanup child", "layout cleanup parent", "cleanup parent"]);
+    }
+
+    #[derive(PartialEq)]
+    struct Subscribes {
+        channel: u32,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Subscribes {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let log = self.log.clone();
+            scope.use_effect(move |_| {
+                log.push("connect");
+                None::<fn() -> ()>
+            }, ());
+            let (channel, log) = (self.channel, self.log.clone());
+            scope.use_effect(move |_| {
+                log.push(format!("subscribe {}", channel));
+                let log = log.clone();
+                Some(move || log.push(format!("unsubscribe {}", channel)))
+            }, self.channel);
+            VNode::Native(String::from("subscribes"))
+        }
+    }
+
+    #[test]
+    fn only_effects_with_a_changed_basis_rerun_after_their_cleanup() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let log = Log::new();
+        let root = mount_root(Subscribes { channel: 1, log: log.clone() }, &updater);
+        assert_eq!(log.take(), vec!["connect", "subscribe 1"]);
+
+        update_root(&root, Subscribes { channel: 2, log: log.clone() }, &updater);
+        assert_eq!(log.take(), vec!["unsubscribe 1", "subscribe 2"]);
+
+        update_root(&root, Subscribes { channel: 2, log: log.clone() }, &updater);
+        assert!(log.take().is_empty());
+    }
+
+    #[derive(PartialEq)]
+    struct CountsForever {
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for CountsForever {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let (count, count_handle) = scope.use_state(0);
+            self.log.push(format!("render {}", count));
+            scope.use_effect_always(move |handle| {
+                handle.update_state_map(count_handle, |count| count + 1);
+                None::<fn() -> ()>
+            });
+            VNode::Native(String::from("counts forever"))
+        }
+    }
+
+    #[test]
+    fn flush_gives_up_on_updates_that_never_settle() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let log = Log::new();
+        let _root = mount_root(CountsForever { log: log.clone() }, &updater);
+        assert_eq!(log.take().len(), FLUSH_ROUND_LIMIT);
+        assert!(!updater.try_borrow().unwrap().has_pending_work());
+    }
+
+    #[derive(PartialEq)]
+    struct ExportsCount {
+        slot: RefObject<StateSetter<u32>>,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for ExportsCount {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let (count, setter) = scope.use_state_setter(0);
+            self.slot.replace(Some(setter));
+            self.log.push(format!("count {}", count));
+            VNode::Native(String::from("exports count"))
+        }
+    }
+
+    #[test]
+    fn batched_updates_render_once_when_the_batch_ends() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let (slot, log) = (RefObject::new(), Log::new());
+        let _root = mount_root(ExportsCount { slot: slot.clone(), log: log.clone() }, &updater);
+        let setter = slot.borrow_mut().take().unwrap();
+        log.take();
+
+        batch(|| {
+            setter.set(1);
+            setter.set(2);
+            setter.set_map(|count| count + 1);
+            assert!(log.take().is_empty());
+        });
+        assert_eq!(log.take(), vec!["count 3"]);
+
+        setter.set(4);
+        setter.set(5);
+        assert_eq!(log.take(), vec!["count 4", "count 5"]);
+    }
+
+    #[derive(PartialEq)]
+    struct WrapsCount {
+        slot: RefObject<StateSetter<u32>>,
+        child: (RefObject<StateSetter<u32>>, Log),
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for WrapsCount {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let (count, setter) = scope.use_state_setter(0);
+            self.slot.replace(Some(setter));
+            self.log.push(format!("parent {}", count));
+            VNode::component(VComponentElement::new(ExportsCount { slot: self.child.0.clone(), log: self.child.1.clone() }, None))
+        }
+    }
+
+    #[test]
+    fn a_child_marked_dirty_before_its_parent_renders_once() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let (slot, child_slot, log) = (RefObject::new(), RefObject::new(), Log::new());
+        let _root = mount_root(WrapsCount { slot: slot.clone(), child: (child_slot.clone(), log.clone()), log: log.clone() }, &updater);
+        let setter = slot.borrow_mut().take().unwrap();
+        let child_setter = child_slot.borrow_mut().take().unwrap();
+        log.take();
+
+        batch(|| {
+            child_setter.set(1);
+            setter.set(1);
+        });
+        assert_eq!(log.take(), vec!["parent 1", "count 1"]);
+    }
+
+    #[test]
+    fn a_panicking_batch_still_ends() {
+        let updater = Rc::new(RefCell::new(Updater::new()));
+        let (slot, log) = (RefObject::new(), Log::new());
+        let _root = mount_root(ExportsCount { slot: slot.clone(), log: log.clone() }, &updater);
+        let setter = slot.borrow_mut().take().unwrap();
+        log.take();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            batch(|| {
+                setter.set(1);
+                panic!("handler failed");
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(log.take(), vec!["count 1"]);
+
+        setter.set(2);
+        assert_eq!(log.take(), vec!["count 2"]);
+    }
+
+    #[test]
+    fn manual_scheduler_renders_only_when_stepped() {
+        let scheduler = Rc::new(ManualScheduler::new());
+        let mut updater = Updater::new();
+        updater.set_scheduler(scheduler.clone());
+        let updater = Rc::new(RefCell::new(updater));
+        let (slot, log) = (RefObject::new(), Log::new());
+        let _root = mount_root(ExportsCount { slot: slot.clone(), log: log.clone() }, &updater);
+        let setter = slot.borrow_mut().take().unwrap();
+        assert_eq!(log.take(), vec!["count 0"]);
+
+        setter.set(1);
+        assert!(log.take().is_empty());
+        assert_eq!(scheduler.pending_count(), 1);
+        scheduler.run_pending();
+        assert_eq!(log.take(), vec!["count 1"]);
+        assert_eq!(scheduler.pending_count(), 0);
+
+        setter.set(2);
+        setter.set(3);
+        assert_eq!(scheduler.pending_count(), 1);
+        scheduler.run_pending();
+        assert_eq!(log.take(), vec!["count 3"]);
+    }
+
+    #[derive(PartialEq)]
+    struct Twig {
+        name: &'static str,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Twig {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let (name, log) = (self.name, self.log.clone());
+            log.push(format!("render {}", name));
+            scope.use_effect(move |_| {
+                log.push(format!("effect {}", name));
+                None::<fn() -> ()>
+            }, ());
+            VNode::Native(String::from(self.name))
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct Stem {
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Stem {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let log = self.log.clone();
+            log.push("render stem");
+            scope.use_effect(move |_| {
+                log.push("effect stem");
+                None::<fn() -> ()>
+            }, ());
+            VNode::Native(String::from("stem"))
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct Grows {
+        stem: bool,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Grows {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let log = self.log.clone();
+            log.push("render root");
+            scope.use_effect(move |_| {
+                log.push("effect root");
+                None::<fn() -> ()>
+            }, self.stem);
+            if self.stem {
+                VNode::component(VComponentElement::new(Stem { log: self.log.clone() }, None))
+            } else {
+                VNode::component(VComponentElement::new(Twig { name: "twig", log: self.log.clone() }, None))
+            }
+        }
+    }
+
+    #[test]
+    fn time_sliced_roots_render_new_and_replaced_children_in_later_slices() {
+        let scheduler = Rc::new(ManualScheduler::new());
+        let mut updater = Updater::new();
+        updater.set_scheduler(scheduler.clone());
+        updater.set_time_slicing(1);
+        let updater = Rc::new(RefCell::new(updater));
+        let log = Log::new();
+        let root = mount_root(Grows { stem: false, log: log.clone() }, &updater);
+        assert!(log.take().is_empty());
+
+        scheduler.run_slices(&NoDeadline);
+        assert_eq!(log.take(), vec!["render root"]);
+        scheduler.run_pending();
+        assert_eq!(log.take(), vec!["render twig", "effect twig", "effect root"]);
+
+        update_root(&root, Grows { stem: true, log: log.clone() }, &updater);
+        scheduler.run_slices(&NoDeadline);
+        assert_eq!(log.take(), vec!["render root"]);
+        scheduler.run_pending();
+        assert_eq!(log.take(), vec!["render stem", "effect stem", "effect root"]);
+    }
+
+    // Flushes right away but keeps slices until they are run by hand, so
+    // transitions stay pending while urgent updates come in.
+    struct HoldsSlices {
+        slices: RefCell<Vec<SliceTask>>
+    }
+
+    impl Scheduler for HoldsSlices {
+        fn schedule_flush(&self, flush: DeferredTask) {
+            flush();
         }
+
+        fn schedule_slice(&self, slice: SliceTask) {
+            self.slices.try_borrow_mut().unwrap().push(slice);
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct Pair {
+        fast: (RefObject<StateSetter<u32>>, Log),
+        slow: (RefObject<StateSetter<u32>>, Log)
+    }
+
+    impl ComponentModel<String, ()> for Pair {
+        fn render(&self, _: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let child = |(slot, log): &(RefObject<StateSetter<u32>>, Log)| {
+                VNode::component(VComponentElement::new(ExportsCount { slot: slot.clone(), log: log.clone() }, None))
+            };
+            VNode::Fragment(vec![(String::from("fast"), child(&self.fast)), (String::from("slow"), child(&self.slow))])
+        }
+    }
+
+    #[test]
+    fn urgent_updates_flush_while_a_transition_is_pending() {
+        let scheduler = Rc::new(HoldsSlices { slices: RefCell::new(vec![]) });
+        let mut updater = Updater::new();
+        updater.set_scheduler(scheduler.clone());
+        let updater = Rc::new(RefCell::new(updater));
+        let (fast, slow) = ((RefObject::new(), Log::new()), (RefObject::new(), Log::new()));
+        let _root = mount_root(Pair { fast: fast.clone(), slow: slow.clone() }, &updater);
+        let fast_setter = fast.0.borrow_mut().take().unwrap();
+        let slow_setter = slow.0.borrow_mut().take().unwrap();
+        fast.1.take();
+        slow.1.take();
+
+        start_transition(|| slow_setter.set(1));
+        fast_setter.set(1);
+        assert_eq!(fast.1.take(), vec!["count 1"]);
+        assert!(slow.1.take().is_empty());
+
+        let slices = std::mem::take(&mut *scheduler.slices.try_borrow_mut().unwrap());
+        for slice in slices.into_iter() {
+            slice(&NoDeadline);
+        }
+        assert_eq!(slow.1.take(), vec!["count 1"]);
+        assert!(fast.1.take().is_empty());
+    }
+
+    struct Expired;
+
+    impl Deadline for Expired {
+        fn should_yield(&self) -> bool {
+            true
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct ShowsCount {
+        name: &'static str,
+        slot: RefObject<StateSetter<u32>>,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for ShowsCount {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            let (count, setter) = scope.use_state_setter(0);
+            self.slot.replace(Some(setter));
+            let (name, log) = (self.name, self.log.clone());
+            log.push(format!("render {} {}", name, count));
+            scope.use_effect(move |_| {
+                log.push(format!("effect {} {}", name, count));
+                None::<fn() -> ()>
+            }, count);
+            VNode::Native(String::from(self.name))
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct Trio {
+        slots: [RefObject<StateSetter<u32>>; 3],
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for Trio {
+        fn render(&self, _: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            VNode::Fragment(["a", "b", "c"].iter().zip(self.slots.iter()).map(|(name, slot)| {
+                (String::from(*name), VNode::component(VComponentElement::new(ShowsCount { name, slot: slot.clone(), log: self.log.clone() }, None)))
+            }).collect())
+        }
+    }
+
+    #[test]
+    fn urgent_flushes_finish_a_started_transition_before_committing() {
+        let scheduler = Rc::new(HoldsSlices { slices: RefCell::new(vec![]) });
+        let log = Log::new();
+        let mut updater = Updater::new();
+        updater.set_scheduler(scheduler.clone());
+        let commits = log.clone();
+        updater.set_committer(move || commits.push("commit"));
+        let updater = Rc::new(RefCell::new(updater));
+        let slots = [RefObject::new(), RefObject::new(), RefObject::new()];
+        let _root = mount_root(Trio { slots: slots.clone(), log: log.clone() }, &updater);
+        let setters: Vec<StateSetter<u32>> = slots.iter().map(|slot| slot.borrow_mut().take().unwrap()).collect();
+        log.take();
+
+        start_transition(|| {
+            setters[0].set(1);
+            setters[1].set(1);
+        });
+        assert_eq!(log.take(), vec!["commit"]);
+        let slices = std::mem::take(&mut *scheduler.slices.try_borrow_mut().unwrap());
+        for slice in slices.into_iter() {
+            slice(&Expired);
+        }
+        assert_eq!(log.take(), vec!["render a 1"]);
+
+        setters[2].set(1);
+        assert_eq!(log.take(), vec!["render c 1", "render b 1", "commit", "effect a 1", "effect c 1", "effect b 1", "commit"]);
+    }
+
+    #[derive(PartialEq)]
+    struct Replaces {
+        replaced: bool,
+        log: Log
     }
-    for e in effects.into_iter() {
-        e.execute();
+
+    impl ComponentModel<String, ()> for Replaces {
+        fn render(&self, _: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            if self.replaced {
+                VNode::component(VComponentElement::new(Twig { name: "new", log: self.log.clone() }, None))
+            } else {
+                VNode::component(VComponentElement::new(Nested { name: "old", child: None, log: self.log.clone() }, None))
+            }
+        }
+    }
+
+    #[test]
+    fn cleanups_of_removed_components_run_after_the_commit() {
+        let log = Log::new();
+        let mut updater = Updater::new();
+        let commits = log.clone();
+        updater.set_committer(move || commits.push("commit"));
+        let updater = Rc::new(RefCell::new(updater));
+        let root = mount_root(Replaces { replaced: false, log: log.clone() }, &updater);
+        log.take();
+
+        update_root(&root, Replaces { replaced: true, log: log.clone() }, &updater);
+        assert_eq!(log.take(), vec!["render new", "commit", "layout cleanup old", "cleanup old", "effect new", "commit"]);
+
+        let root = mount_root(Nested { name: "parent", child: Some("child"), log: log.clone() }, &updater);
+        log.take();
+        unmount_root(&root, &updater);
+        assert_eq!(log.take(), vec!["commit", "layout cleanup child", "cleanup child", "layout cleanup parent", "cleanup parent", "commit"]);
+    }
+
+    struct LogsCleanup {
+        log: Log
     }
-}
\ No newline at end of file
+
+    impl Hook for LogsCleanup {
+        type Input = Log;
+        type Output = ();
+
+        fn init(log: Log, _: EffectHandle) -> LogsCleanup {
+            LogsCleanup { log }
+        }
+
+        fn update(&mut self, log: Log) {
+            self.log = log;
+        }
+
+        fn output(&self) {
+        }
+
+        fn cleanup(&mut self) {
+            self.log.push("hook cleanup");
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct UsesHook {
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for UsesHook {
+        fn render(&self, scope: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            scope.use_hook::<LogsCleanup>(self.log.clone());
+            VNode::Native(String::from("hook"))
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct ShowsHook {
+        shown: bool,
+        log: Log
+    }
+
+    impl ComponentModel<String, ()> for ShowsHook {
+        fn render(&self, _: &mut ComponentScope, _: &NilRef) -> VNode<String> {
+            if self.shown {
+                VNode::component(VComponentElement::new(UsesHook { log: self.log.clone() }, None))
+            } else {
+                VNode::Native(String::from("hidden"))
+            }
+        }
+    }
+
+    #[test]
+    fn custom_hook_cleanups_of_removed_components_run_after_the_commit() {
+        let log = Log::new();
+        let mut updater = Updater::new();
+        let commits = log.clone();
+        updater.set_committer(move || commits.push("commit"));
+        let updater = Rc::new(RefCell::new(updater));
+        let root = mount_root(ShowsHook { shown: true, log: log.clone() }, &updater);
+        log.take();
+
+        update_root(&root, ShowsHook { shown: false, log: log.clone() }, &updater);
+        assert_eq!(log.take(), vec!["commit", "hook cleanup", "commit"]);
+    }
+}
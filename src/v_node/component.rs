@@ -35,7 +35,7 @@ impl_downcast!(VComponentElementT<VNativeNode>);
 
 impl<Model: ComponentModel<VNativeNode, Ref> + 'static, Ref: 'static, VNativeNode: 'static> VComponentElementT<VNativeNode> for VComponentElement<VNativeNode, Model, Ref> {
     fn render(&self, scope: &mut Scope) -> VNode<VNativeNode> {
-        scope.mark_start_render();
+        scope.mark_start_render(self.component_model.name());
         let result = self.component_model.render(&mut scope.component_scope, &self.ref_object);
         scope.mark_end_render();
         result
@@ -1,10 +1,11 @@
 use std::rc::Rc;
 use std::cell::RefCell;
-use crate::scope::{ContextLink, ContextNode,ContextNodeT};
+use crate::scope::{Context, ContextLink, ContextNode,ContextNodeT};
 use crate::v_node::v_node::VNode;
 use crate::v_node::node_comparison::NodeComparisonResult;
 
 pub struct VContext<VNativeNode: 'static, T: 'static + PartialEq> {
+    pub context: &'static Context<T>,
     pub value: T,
     pub children: Box<VNode<VNativeNode>>
 }
@@ -19,9 +20,10 @@ impl<VNativeNode, T: PartialEq> VContextT<VNativeNode> for VContext<VNativeNode,
     fn to_context_link(self: Box<Self>, parent: ContextLink) -> (Rc<dyn ContextNodeT>, VNode<VNativeNode>) {
         (
             Rc::new(ContextNode {
+                key: self.context.key(),
                 parent,
                 value: RefCell::new(Rc::new(self.value)),
-                renderers: RefCell::new(vec![])
+                subscriptions: RefCell::new(vec![])
             }),
             *self.children
         )
@@ -33,8 +35,9 @@ impl<VNativeNode, T: PartialEq> VContextT<VNativeNode> for VContext<VNativeNode,
         *self.children
     }
 
-    fn compare(self: &Self, context_node: Rc<dyn ContextNodeT>) -> NodeComparisonResult {
+    fn compare(&self, context_node: Rc<dyn ContextNodeT>) -> NodeComparisonResult {
         match context_node.downcast_rc::<ContextNode<T>>() {
+            Ok(same_context) if same_context.key != self.context.key() => NodeComparisonResult::DifferentType,
             Ok(same_context) => if same_context.value.try_borrow().unwrap().as_ref().eq(&self.value) {
                 NodeComparisonResult::Equal
             } else {
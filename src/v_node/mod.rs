@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 mod v_node;
 mod component;
 mod context;
@@ -7,7 +8,9 @@ pub use crate::v_node::component::{ComponentModel, VComponentElementT, VComponen
 pub use crate::v_node::context::{VContextT, VContext};
 pub use crate::v_node::v_node::VNode;
 pub use crate::v_node::node_comparison::NodeComparisonResult;
-use crate::scope::RefObject;
+use crate::scope::{RefObject, Context};
+#[cfg(debug_assertions)]
+use crate::scope::STRICT_MODE;
 
 pub fn h<VNativeNode, Model: ComponentModel<VNativeNode, Ref> + 'static, Ref: 'static>(component_model: Model, ref_object: Option<RefObject<Ref>>) -> VNode<VNativeNode>
     where
@@ -18,9 +21,20 @@ pub fn h<VNativeNode, Model: ComponentModel<VNativeNode, Ref> + 'static, Ref: 's
     ))
 }
 
-pub fn ct<T: 'static + PartialEq, VNativeNode: 'static>(value: T, children: VNode<VNativeNode>) -> VNode<VNativeNode> {
+pub fn ct<T: 'static + PartialEq, VNativeNode: 'static>(context: &'static Context<T>, value: T, children: VNode<VNativeNode>) -> VNode<VNativeNode> {
     VNode::Context(Box::new(VContext {
+        context,
         value,
         children: Box::new(children),
     }))
+}
+
+#[cfg(debug_assertions)]
+pub fn strict<VNativeNode: 'static>(children: VNode<VNativeNode>) -> VNode<VNativeNode> {
+    ct(&STRICT_MODE, true, children)
+}
+
+#[cfg(not(debug_assertions))]
+pub fn strict<VNativeNode: 'static>(children: VNode<VNativeNode>) -> VNode<VNativeNode> {
+    children
 }
\ No newline at end of file